@@ -0,0 +1,58 @@
+// usage: perft [--divide] <depth> [fen]
+// counts the leaf nodes of the legal move tree from the start position or the given FEN
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+use chess_core::{divide, perft, Position};
+
+const USAGE: &str = "usage: perft [--divide] <depth> [fen]";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let split = match args.iter().position(|arg| arg == "--divide") {
+        Some(i) => { args.remove(i); true },
+        None => false,
+    };
+
+    let depth = match args.first().map(|arg| arg.parse::<u32>()) {
+        Some(Ok(depth)) => depth,
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        },
+    };
+
+    // the FEN may come as one quoted argument or as its six fields
+    let mut position = if args.len() > 1 {
+        match Position::from_fen(&args[1..].join(" ")) {
+            Ok(position) => position,
+            Err(e) => {
+                eprintln!("invalid FEN: {}", e);
+                return ExitCode::from(2);
+            },
+        }
+    } else {
+        Position::new()
+    };
+
+    let start = Instant::now();
+    let nodes = if split {
+        let mut moves = divide(&mut position, depth);
+        moves.sort_by_key(|(mv, _)| mv.uci());
+        for (mv, nodes) in &moves {
+            println!("{}: {}", mv.uci(), nodes);
+        }
+        println!();
+        moves.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft(&mut position, depth)
+    };
+    let elapsed = start.elapsed();
+
+    println!("nodes: {}", nodes);
+    println!("time: {} ms ({:.0} nodes/s)", elapsed.as_millis(), nodes as f64 / elapsed.as_secs_f64().max(1e-9));
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,214 @@
+// usage: uci
+// speaks the Universal Chess Interface on stdin and stdout, so other GUIs and
+// tournament managers can run the engine
+
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chess_core::{Engine, Limits, Player, Position, Score, SearchInfo, DEFAULT_HASH_MB, MAX_SKILL_LEVEL};
+
+struct Uci {
+    engine: Arc<Mutex<Engine>>,
+    stop: Arc<AtomicBool>,
+    position: Position,
+    // hashes of the positions before `position`, for the repetition rule
+    history: Vec<u64>,
+    search: Option<JoinHandle<()>>,
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Cp(cp) => format!("cp {}", cp),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+    let millis = info.time.as_millis() as u64;
+    let nps = info.nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = info.pv.iter().map(|mv| mv.uci()).collect();
+
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        info.depth, info.multipv, score, info.nodes, nps, millis, pv.join(" "),
+    )
+}
+
+// a slice of the remaining clock, never so much that the flag falls
+fn time_for_move(time_left: u64, increment: u64, moves_to_go: Option<u64>) -> Duration {
+    let moves = moves_to_go.unwrap_or(30).max(1);
+    let budget = time_left / moves + increment * 3 / 4;
+    Duration::from_millis(budget.min(time_left.saturating_sub(50)).max(10))
+}
+
+impl Uci {
+    fn new() -> Self {
+        Uci {
+            engine: Arc::new(Mutex::new(Engine::new())),
+            stop: Arc::new(AtomicBool::new(false)),
+            position: Position::new(),
+            history: Vec::new(),
+            search: None,
+        }
+    }
+
+    fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            let _ = search.join();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    // position [startpos | fen <fen>] [moves <move>...]
+    fn position(&mut self, args: &[&str]) {
+        let moves_at = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+
+        let position = match args.first() {
+            Some(&"startpos") => Ok(Position::new()),
+            Some(&"fen") => Position::from_fen(&args[1..moves_at].join(" ")).map_err(|e| e.to_string()),
+            _ => Err(String::from("expected startpos or fen")),
+        };
+        let mut position = match position {
+            Ok(position) => position,
+            Err(e) => {
+                println!("info string invalid position: {}", e);
+                return;
+            },
+        };
+
+        let mut history = Vec::new();
+        for uci in args.iter().skip(moves_at + 1) {
+            match position.uci_move(uci) {
+                Some(mv) => {
+                    history.push(position.hash());
+                    position.make_move(&mv);
+                },
+                None => {
+                    println!("info string illegal move {}", uci);
+                    break;
+                },
+            }
+        }
+
+        self.position = position;
+        self.history = history;
+    }
+
+    // go [depth N] [nodes N] [movetime MS] [wtime MS] [btime MS] [winc MS] [binc MS] [movestogo N] [infinite]
+    fn go(&mut self, args: &[&str]) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+
+        let mut limits = Limits::default();
+        let mut clock: [Option<u64>; 2] = [None, None];
+        let mut increment: [u64; 2] = [0, 0];
+        let mut moves_to_go = None;
+        let mut infinite = false;
+
+        let mut tokens = args.iter();
+        while let Some(token) = tokens.next() {
+            let mut value = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+            match *token {
+                "depth" => { limits.depth = value().map(|depth| depth as u32); },
+                "nodes" => { limits.nodes = value(); },
+                "movetime" => { limits.movetime = value().map(Duration::from_millis); },
+                "wtime" => { clock[Player::White as usize] = value(); },
+                "btime" => { clock[Player::Black as usize] = value(); },
+                "winc" => { increment[Player::White as usize] = value().unwrap_or(0); },
+                "binc" => { increment[Player::Black as usize] = value().unwrap_or(0); },
+                "movestogo" => { moves_to_go = value(); },
+                "infinite" => { infinite = true; },
+                _ => {},
+            }
+        }
+
+        let turn = self.position.turn as usize;
+        if let (None, Some(time_left)) = (limits.movetime, clock[turn]) {
+            limits.movetime = Some(time_for_move(time_left, increment[turn], moves_to_go));
+        }
+
+        let engine = self.engine.clone();
+        let stop = self.stop.clone();
+        let position = self.position.clone();
+        let history = self.history.clone();
+
+        self.search = Some(std::thread::spawn(move || {
+            let best = engine.lock().unwrap().search(&position, &history, &limits, &stop, |info| {
+                println!("{}", info_line(info));
+            });
+
+            // "go infinite" answers only after "stop", even when the search ran out of depth
+            while infinite && !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+
+            match best {
+                Some(mv) => println!("bestmove {}", mv.uci()),
+                None => println!("bestmove 0000"),
+            }
+        }));
+    }
+
+    // setoption name <name> [value <value>]
+    fn set_option(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|arg| *arg == "value").unwrap_or(args.len());
+        let name = args.get(1..value_at).unwrap_or_default().join(" ").to_lowercase();
+        let value = args.get(value_at + 1..).unwrap_or_default().join(" ");
+
+        // a running search holds the engine, and "go infinite" would never end by itself
+        self.stop();
+        let mut engine = self.engine.lock().unwrap();
+        match (name.as_str(), value.parse::<usize>()) {
+            ("hash", Ok(megabytes)) => { engine.set_hash_size(megabytes); },
+            ("multipv", Ok(lines)) => { engine.set_multi_pv(lines); },
+            ("skill level", Ok(level)) => { engine.set_skill_level(level as u32); },
+            ("clear hash", _) => { engine.clear(); },
+            _ => { println!("info string unknown option {}", name); },
+        }
+    }
+}
+
+fn main() {
+    let mut uci = Uci::new();
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = tokens.split_first() else {
+            continue;
+        };
+
+        match *command {
+            "uci" => {
+                println!("id name chess");
+                println!("id author qubitkiwi");
+                println!("option name Hash type spin default {} min 1 max 1024", DEFAULT_HASH_MB);
+                println!("option name MultiPV type spin default 1 min 1 max 64");
+                println!("option name Skill Level type spin default {} min 0 max {}", MAX_SKILL_LEVEL, MAX_SKILL_LEVEL);
+                println!("option name Clear Hash type button");
+                println!("uciok");
+            },
+            "isready" => { println!("readyok"); },
+            "ucinewgame" => {
+                uci.stop();
+                uci.engine.lock().unwrap().clear();
+                uci.position = Position::new();
+                uci.history = Vec::new();
+            },
+            "position" => { uci.position(args); },
+            "go" => { uci.go(args); },
+            "stop" => { uci.stop(); },
+            "setoption" => { uci.set_option(args); },
+            "quit" => { break; },
+            _ => { println!("info string unknown command {}", command); },
+        }
+    }
+
+    uci.stop();
+}
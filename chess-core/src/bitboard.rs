@@ -0,0 +1,130 @@
+use std::sync::OnceLock;
+
+use crate::{Player, Point, CHESS_LEHGT};
+
+// one bit per square, bit 0 is a8 and bit 63 is h1 (see Point::square)
+pub type Bitboard = u64;
+
+// a8 is light, so every square with h + w even
+pub const LIGHT_SQUARES: Bitboard = 0xaa55_aa55_aa55_aa55;
+
+// (dh, dw) steps; the first four walk towards higher square numbers
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+const KNIGHT_STEPS: [(i32, i32); 8] = [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (1, -2), (-1, 2), (-1, -2)];
+
+struct Tables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    // squares a pawn of that colour attacks
+    pawn: [[Bitboard; 64]; 2],
+    // [direction][square], rook directions first then bishop directions
+    rays: [[Bitboard; 64]; 8],
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Tables { knight: [0; 64], king: [0; 64], pawn: [[0; 64]; 2], rays: [[0; 64]; 8] };
+
+        for square in 0..64 {
+            let king_steps: Vec<(i32, i32)> = ROOK_DIRECTIONS.iter().chain(BISHOP_DIRECTIONS.iter()).copied().collect();
+            tables.knight[square] = steps(square, &KNIGHT_STEPS);
+            tables.king[square] = steps(square, &king_steps);
+            tables.pawn[Player::White as usize][square] = steps(square, &[(-1, -1), (-1, 1)]);
+            tables.pawn[Player::Black as usize][square] = steps(square, &[(1, -1), (1, 1)]);
+
+            for (direction, step) in ROOK_DIRECTIONS.iter().chain(BISHOP_DIRECTIONS.iter()).enumerate() {
+                tables.rays[direction][square] = ray(square, *step);
+            }
+        }
+
+        tables
+    })
+}
+
+fn offset(square: usize, (dh, dw): (i32, i32)) -> Option<usize> {
+    let h = (square / CHESS_LEHGT) as i32 + dh;
+    let w = (square % CHESS_LEHGT) as i32 + dw;
+    if (0..CHESS_LEHGT as i32).contains(&h) && (0..CHESS_LEHGT as i32).contains(&w) {
+        Some(h as usize * CHESS_LEHGT + w as usize)
+    } else {
+        None
+    }
+}
+
+fn steps(square: usize, list: &[(i32, i32)]) -> Bitboard {
+    list.iter()
+        .filter_map(|step| offset(square, *step))
+        .fold(0, |bb, to| bb | 1 << to)
+}
+
+fn ray(square: usize, step: (i32, i32)) -> Bitboard {
+    let mut bb = 0;
+    let mut current = square;
+    while let Some(to) = offset(current, step) {
+        bb |= 1 << to;
+        current = to;
+    }
+    bb
+}
+
+// classical ray attacks: cut each ray at its first blocker, the blocker itself stays attacked
+fn slide(square: usize, occupied: Bitboard, directions: std::ops::Range<usize>) -> Bitboard {
+    let tables = tables();
+    let mut attacks = 0;
+
+    for direction in directions {
+        let ray = tables.rays[direction][square];
+        let blockers = ray & occupied;
+        attacks |= ray;
+        if blockers != 0 {
+            // rays with dh > 0, or dh == 0 and dw > 0, run towards higher squares
+            let forward = matches!(direction, 0 | 1 | 4 | 5);
+            let blocker = if forward { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+            attacks &= !tables.rays[direction][blocker as usize];
+        }
+    }
+
+    attacks
+}
+
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    slide(square, occupied, 0..4)
+}
+
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    slide(square, occupied, 4..8)
+}
+
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+pub fn knight_attacks(square: usize) -> Bitboard {
+    tables().knight[square]
+}
+
+pub fn king_attacks(square: usize) -> Bitboard {
+    tables().king[square]
+}
+
+pub fn pawn_attacks(owner: Player, square: usize) -> Bitboard {
+    tables().pawn[owner as usize][square]
+}
+
+// iterates the set squares from lowest to highest
+pub fn squares(mut bb: Bitboard) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None;
+        }
+        let square = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        Some(square)
+    })
+}
+
+pub fn points(bb: Bitboard) -> Vec<Point> {
+    squares(bb).map(Point::from_square).collect()
+}
@@ -0,0 +1,187 @@
+use crate::bitboard::Bitboard;
+use crate::{zobrist, CHESS_LEHGT};
+
+#[derive(Clone, PartialEq, Eq, Hash, Copy, Debug)]
+pub enum ChessPiece {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum Player {
+    White,
+    Black,
+}
+
+impl Player {
+    pub fn enemy(self) -> Player {
+        match self {
+            Player::White => Player::Black,
+            Player::Black => Player::White,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Player::White => "White",
+            Player::Black => "Black",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PieceState {
+    pub owner: Player,
+    pub piece: ChessPiece,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub h: usize,
+    pub w: usize,
+}
+
+impl Point {
+    // 0 is a8, 63 is h1, the same order as the rows of the board
+    pub fn square(&self) -> usize {
+        self.h * CHESS_LEHGT + self.w
+    }
+
+    pub fn from_square(square: usize) -> Point {
+        Point { h: square / CHESS_LEHGT, w: square % CHESS_LEHGT }
+    }
+}
+
+// one bitboard per piece kind and per colour, plus a mailbox for quick lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+    pub pieces: [Bitboard; 6],
+    pub colors: [Bitboard; 2],
+    squares: [Option<PieceState>; 64],
+    hash: u64,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Board {
+    pub fn empty() -> Self {
+        Board { pieces: [0; 6], colors: [0; 2], squares: [None; 64], hash: 0 }
+    }
+
+    pub fn get(&self, p: &Point) -> Option<PieceState> {
+        self.squares[p.square()]
+    }
+
+    pub fn set(&mut self, p: &Point, piece_state: Option<PieceState>) {
+        let square = p.square();
+
+        if let Some(old) = self.squares[square] {
+            self.pieces[old.piece as usize] &= !(1 << square);
+            self.colors[old.owner as usize] &= !(1 << square);
+            self.hash ^= zobrist::piece(old, square);
+        }
+        if let Some(new) = piece_state {
+            self.pieces[new.piece as usize] |= 1 << square;
+            self.colors[new.owner as usize] |= 1 << square;
+            self.hash ^= zobrist::piece(new, square);
+        }
+
+        self.squares[square] = piece_state;
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn bitboard(&self, owner: Player, piece: ChessPiece) -> Bitboard {
+        self.colors[owner as usize] & self.pieces[piece as usize]
+    }
+
+    // Zobrist hash of the piece placement only
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+// true once the piece has moved or the rook has been captured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Castling {
+    pub w_r_rook: bool,
+    pub w_l_rook: bool,
+    pub w_king: bool,
+    pub b_r_rook: bool,
+    pub b_l_rook: bool,
+    pub b_king: bool,
+}
+
+impl Default for Castling {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Castling {
+    pub fn new() -> Self {
+        Castling { w_r_rook: false, w_l_rook: false, w_king: false, b_r_rook: false, b_l_rook: false, b_king: false }
+    }
+
+    // a move from or onto a corner/king square spends that right
+    pub fn update(&mut self, from: &Point, to: &Point) {
+        for p in [from, to] {
+            match (p.h, p.w) {
+                (7, 7) => { self.w_r_rook = true; },
+                (7, 0) => { self.w_l_rook = true; },
+                (7, 4) => { self.w_king = true; },
+                (0, 7) => { self.b_r_rook = true; },
+                (0, 0) => { self.b_l_rook = true; },
+                (0, 4) => { self.b_king = true; },
+                _ => {},
+            }
+        }
+    }
+
+    // K, Q, k, q
+    pub fn rights(&self) -> [bool; 4] {
+        [
+            !self.w_king && !self.w_r_rook,
+            !self.w_king && !self.w_l_rook,
+            !self.b_king && !self.b_r_rook,
+            !self.b_king && !self.b_l_rook,
+        ]
+    }
+}
+
+
+pub fn init_board() -> Board {
+    let mut board = Board::empty();
+
+    // ♜♞♝♛♚♝♞♜
+    let chess_seq: [ChessPiece; CHESS_LEHGT] = [
+                        ChessPiece::Rook,
+                        ChessPiece::Knight,
+                        ChessPiece::Bishop,
+                        ChessPiece::Queen,
+                        ChessPiece::King,
+                        ChessPiece::Bishop,
+                        ChessPiece::Knight,
+                        ChessPiece::Rook,
+                        ];
+
+    for (w, piece) in chess_seq.into_iter().enumerate() {
+        board.set(&Point { h: 0, w }, Some(PieceState { owner: Player::Black, piece }));
+        board.set(&Point { h: 1, w }, Some(PieceState { owner: Player::Black, piece: ChessPiece::Pawn }));
+
+        board.set(&Point { h: 6, w }, Some(PieceState { owner: Player::White, piece: ChessPiece::Pawn }));
+        board.set(&Point { h: 7, w }, Some(PieceState { owner: Player::White, piece }));
+    }
+
+    board
+}
@@ -0,0 +1,137 @@
+use crate::bitboard::squares;
+use crate::{ChessPiece, Player, Position};
+
+pub const PAWN_VALUE: i32 = 100;
+
+pub fn piece_value(piece: ChessPiece) -> i32 {
+    match piece {
+        ChessPiece::Pawn => PAWN_VALUE,
+        ChessPiece::Knight => 320,
+        ChessPiece::Bishop => 330,
+        ChessPiece::Rook => 500,
+        ChessPiece::Queen => 900,
+        ChessPiece::King => 0,
+    }
+}
+
+// piece-square tables from White's side, a8 first like Point::square; Black reads them mirrored
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+const KING_MIDDLE_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+const KING_END_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+// without queens, or with little else besides them, the king should walk to the centre
+fn is_endgame(position: &Position) -> bool {
+    let board = &position.board;
+    let queens = board.pieces[ChessPiece::Queen as usize];
+    let minors_and_rooks = board.pieces[ChessPiece::Knight as usize]
+        | board.pieces[ChessPiece::Bishop as usize]
+        | board.pieces[ChessPiece::Rook as usize];
+
+    queens == 0 || minors_and_rooks.count_ones() <= 2
+}
+
+// material plus piece-square tables, in centipawns from the side to move
+pub fn evaluate(position: &Position) -> i32 {
+    let endgame = is_endgame(position);
+    let mut score = 0;
+
+    for owner in [Player::White, Player::Black] {
+        let sign = if owner == Player::White { 1 } else { -1 };
+
+        for piece in [ChessPiece::King, ChessPiece::Queen, ChessPiece::Rook, ChessPiece::Bishop, ChessPiece::Knight, ChessPiece::Pawn] {
+            let table = match piece {
+                ChessPiece::Pawn => &PAWN_TABLE,
+                ChessPiece::Knight => &KNIGHT_TABLE,
+                ChessPiece::Bishop => &BISHOP_TABLE,
+                ChessPiece::Rook => &ROOK_TABLE,
+                ChessPiece::Queen => &QUEEN_TABLE,
+                ChessPiece::King if endgame => &KING_END_TABLE,
+                ChessPiece::King => &KING_MIDDLE_TABLE,
+            };
+
+            for square in squares(position.board.bitboard(owner, piece)) {
+                // flipping the rank mirrors the board for Black
+                let square = if owner == Player::White { square } else { square ^ 56 };
+                score += sign * (piece_value(piece) + table[square]);
+            }
+        }
+    }
+
+    match position.turn {
+        Player::White => score,
+        Player::Black => -score,
+    }
+}
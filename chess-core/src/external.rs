@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::{Limits, Move, Position, Score, SearchInfo};
+
+// how long the engine gets to answer "uci" and "isready"
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// how far past the move time the engine may go before it is told to stop
+const MOVETIME_MARGIN: Duration = Duration::from_secs(1);
+// how long the engine gets to send "bestmove" after "stop"
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum ExternalError {
+    Spawn(String, std::io::Error),
+    Io(std::io::Error),
+    Closed,
+    Timeout(&'static str),
+}
+
+impl fmt::Display for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalError::Spawn(path, e) => write!(f, "could not start {}: {}", path, e),
+            ExternalError::Io(e) => write!(f, "could not talk to the engine: {}", e),
+            ExternalError::Closed => write!(f, "the engine exited"),
+            ExternalError::Timeout(command) => write!(f, "the engine did not answer \"{}\"", command),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExternalError {
+    fn from(e: std::io::Error) -> Self {
+        ExternalError::Io(e)
+    }
+}
+
+// a UCI engine running as a child process
+pub struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    // stdout lines, read on a thread of their own so waiting can time out
+    lines: Receiver<String>,
+    pub name: String,
+}
+
+impl fmt::Debug for ExternalEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalEngine").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+// "info depth 12 multipv 1 score cp 35 nodes 12345 time 120 pv e2e4 e7e5", PV moves checked against `position`
+pub fn parse_info(position: &Position, line: &str) -> Option<SearchInfo> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.first() != Some(&"info") {
+        return None;
+    }
+
+    let mut depth = None;
+    let mut multipv = 1;
+    let mut score = None;
+    let mut nodes = 0;
+    let mut time = Duration::ZERO;
+    let mut pv = Vec::new();
+
+    let mut i = 1;
+    while i < tokens.len() {
+        let next = tokens.get(i + 1).copied().unwrap_or_default();
+        match tokens[i] {
+            "depth" => { depth = next.parse().ok(); i += 1; },
+            "multipv" => { multipv = next.parse().unwrap_or(1); i += 1; },
+            "nodes" => { nodes = next.parse().unwrap_or(0); i += 1; },
+            "time" => { time = Duration::from_millis(next.parse().unwrap_or(0)); i += 1; },
+            "score" => {
+                let value = tokens.get(i + 2).and_then(|value| value.parse().ok());
+                score = match next {
+                    "cp" => value.map(Score::Cp),
+                    "mate" => value.map(Score::Mate),
+                    _ => None,
+                };
+                i += 2;
+            },
+            "pv" => {
+                let mut scratch = position.clone();
+                for uci in &tokens[i + 1..] {
+                    let Some(mv) = scratch.uci_move(uci) else {
+                        break;
+                    };
+                    scratch.make_move(&mv);
+                    pv.push(mv);
+                }
+                break;
+            },
+            // the rest of the line is free text
+            "string" => { return None; },
+            _ => {},
+        }
+        i += 1;
+    }
+
+    Some(SearchInfo { depth: depth?, multipv, score: score?, nodes, time, pv })
+}
+
+impl ExternalEngine {
+    // starts the executable and waits for "uciok" and "readyok"
+    pub fn spawn(path: &str) -> Result<ExternalEngine, ExternalError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ExternalError::Spawn(path.to_string(), e))?;
+
+        let stdin = child.stdin.take().ok_or(ExternalError::Closed)?;
+        let stdout = child.stdout.take().ok_or(ExternalError::Closed)?;
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = ExternalEngine { child, stdin, lines, name: path.to_string() };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.receive(HANDSHAKE_TIMEOUT, "uci")?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.is_ready()?;
+
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), ExternalError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration, command: &'static str) -> Result<String, ExternalError> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(ExternalError::Timeout(command)),
+            Err(RecvTimeoutError::Disconnected) => Err(ExternalError::Closed),
+        }
+    }
+
+    pub fn is_ready(&mut self) -> Result<(), ExternalError> {
+        self.send("isready")?;
+        while self.receive(HANDSHAKE_TIMEOUT, "isready")?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), ExternalError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn new_game(&mut self) -> Result<(), ExternalError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    // searches the position reached by `moves` from `start` until the limits are met or `stop` is set;
+    // without limits only `stop` ends the search. An engine that overruns the move time is stopped,
+    // and one that does not answer "stop" is given up on
+    pub fn go(
+        &mut self,
+        start: &Position,
+        moves: &[Move],
+        limits: &Limits,
+        stop: &AtomicBool,
+        mut on_info: impl FnMut(&SearchInfo),
+    ) -> Result<Option<Move>, ExternalError> {
+        let mut position = start.clone();
+        let mut command = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            command.push_str(" moves");
+        }
+        for mv in moves {
+            command.push(' ');
+            command.push_str(&mv.uci());
+            position.make_move(mv);
+        }
+        self.send(&command)?;
+
+        let mut command = String::from("go");
+        if let Some(depth) = limits.depth {
+            command.push_str(&format!(" depth {}", depth));
+        }
+        if let Some(nodes) = limits.nodes {
+            command.push_str(&format!(" nodes {}", nodes));
+        }
+        if let Some(movetime) = limits.movetime {
+            command.push_str(&format!(" movetime {}", movetime.as_millis()));
+        }
+        if command == "go" {
+            command.push_str(" infinite");
+        }
+        self.send(&command)?;
+
+        let deadline = limits.movetime.map(|movetime| Instant::now() + movetime + MOVETIME_MARGIN);
+        let mut stopped: Option<Instant> = None;
+        loop {
+            let now = Instant::now();
+            if stopped.is_none() && (stop.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| now >= deadline)) {
+                self.send("stop")?;
+                stopped = Some(now);
+            }
+            if stopped.is_some_and(|stopped| now - stopped >= STOP_TIMEOUT) {
+                return Err(ExternalError::Timeout("stop"));
+            }
+
+            let line = match self.lines.recv_timeout(Duration::from_millis(20)) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(ExternalError::Closed),
+            };
+
+            if let Some(rest) = line.strip_prefix("bestmove") {
+                let best = rest.split_whitespace().next().unwrap_or_default();
+                return Ok(position.uci_move(best));
+            }
+            if let Some(info) = parse_info(&position, &line) {
+                on_info(&info);
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+
+use crate::{
+    Board, Castling, ChessPiece, PieceState, Player, Point, Position, CHESS_LEHGT,
+    is_checked,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    FieldCount(usize),
+    RankCount(usize),
+    RankLength(usize),
+    InvalidPiece(char),
+    InvalidTurn(String),
+    InvalidCastling(String),
+    CastlingWithoutPieces(char),
+    InvalidEnPassant(String),
+    InvalidHalfmove(String),
+    InvalidFullmove(String),
+    KingCount(Player, usize),
+    PawnOnBackRank,
+    EnemyInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::FieldCount(n) => write!(f, "expected 4 or 6 space separated fields, found {}", n),
+            FenError::RankCount(n) => write!(f, "expected 8 ranks separated by '/', found {}", n),
+            FenError::RankLength(rank) => write!(f, "rank {} does not describe exactly 8 squares", rank),
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece letter or empty square count", c),
+            FenError::InvalidTurn(s) => write!(f, "side to move must be 'w' or 'b', found '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "castling rights must be '-' or a subset of 'KQkq', found '{}'", s),
+            FenError::CastlingWithoutPieces(c) => write!(f, "castling right '{}' needs the king and rook on their starting squares", c),
+            FenError::InvalidEnPassant(s) => write!(f, "en passant square must be '-' or a square on rank 3 or 6 behind a pawn, found '{}'", s),
+            FenError::InvalidHalfmove(s) => write!(f, "halfmove clock must be a non-negative number, found '{}'", s),
+            FenError::InvalidFullmove(s) => write!(f, "fullmove number must be a positive number, found '{}'", s),
+            FenError::KingCount(player, n) => write!(f, "{} must have exactly one king, found {}", player.name(), n),
+            FenError::PawnOnBackRank => write!(f, "pawns cannot stand on the first or last rank"),
+            FenError::EnemyInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+fn piece_to_char(piece: &PieceState) -> char {
+    let c = match piece.piece {
+        ChessPiece::King => 'k',
+        ChessPiece::Queen => 'q',
+        ChessPiece::Rook => 'r',
+        ChessPiece::Bishop => 'b',
+        ChessPiece::Knight => 'n',
+        ChessPiece::Pawn => 'p',
+    };
+
+    match piece.owner {
+        Player::White => c.to_ascii_uppercase(),
+        Player::Black => c,
+    }
+}
+
+fn char_to_piece(c: char) -> Option<PieceState> {
+    let piece = match c.to_ascii_lowercase() {
+        'k' => ChessPiece::King,
+        'q' => ChessPiece::Queen,
+        'r' => ChessPiece::Rook,
+        'b' => ChessPiece::Bishop,
+        'n' => ChessPiece::Knight,
+        'p' => ChessPiece::Pawn,
+        _ => return None,
+    };
+    let owner = if c.is_ascii_uppercase() { Player::White } else { Player::Black };
+
+    Some(PieceState { owner, piece })
+}
+
+// "e3" <-> Point { h: 5, w: 4 }
+pub fn point_to_square(p: &Point) -> String {
+    format!("{}{}", (b'a' + p.w as u8) as char, CHESS_LEHGT - p.h)
+}
+
+pub fn square_to_point(s: &str) -> Option<Point> {
+    let mut chars = s.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?.to_digit(10)? as usize;
+
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !(1..=CHESS_LEHGT).contains(&rank) {
+        return None;
+    }
+
+    Some(Point { h: CHESS_LEHGT - rank, w: file as usize - 'a' as usize })
+}
+
+fn parse_board(field: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != CHESS_LEHGT {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+
+    let mut board = Board::empty();
+
+    for (h, rank) in ranks.iter().enumerate() {
+        let mut w = 0;
+        for c in rank.chars() {
+            if let Some(n) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                w += n as usize;
+            } else {
+                let piece = char_to_piece(c).ok_or(FenError::InvalidPiece(c))?;
+                if w < CHESS_LEHGT {
+                    board.set(&Point { h, w }, Some(piece));
+                }
+                w += 1;
+            }
+
+            if w > CHESS_LEHGT {
+                return Err(FenError::RankLength(CHESS_LEHGT - h));
+            }
+        }
+
+        if w != CHESS_LEHGT {
+            return Err(FenError::RankLength(CHESS_LEHGT - h));
+        }
+    }
+
+    Ok(board)
+}
+
+fn parse_castling(field: &str, board: &Board) -> Result<Castling, FenError> {
+    // start from "everything has moved" and give back the listed rights
+    let mut castling = Castling { w_r_rook: true, w_l_rook: true, w_king: true, b_r_rook: true, b_l_rook: true, b_king: true };
+
+    if field == "-" {
+        return Ok(castling);
+    }
+
+    if field.is_empty() || field.chars().any(|c| !"KQkq".contains(c)) {
+        return Err(FenError::InvalidCastling(field.to_string()));
+    }
+
+    for c in field.chars() {
+        let (owner, h, rook_w) = match c {
+            'K' => (Player::White, 7, 7),
+            'Q' => (Player::White, 7, 0),
+            'k' => (Player::Black, 0, 7),
+            _ => (Player::Black, 0, 0),
+        };
+
+        let king = Some(PieceState { owner, piece: ChessPiece::King });
+        let rook = Some(PieceState { owner, piece: ChessPiece::Rook });
+        if board.get(&Point { h, w: 4 }) != king || board.get(&Point { h, w: rook_w }) != rook {
+            return Err(FenError::CastlingWithoutPieces(c));
+        }
+
+        match c {
+            'K' => { castling.w_king = false; castling.w_r_rook = false; },
+            'Q' => { castling.w_king = false; castling.w_l_rook = false; },
+            'k' => { castling.b_king = false; castling.b_r_rook = false; },
+            _ => { castling.b_king = false; castling.b_l_rook = false; },
+        }
+    }
+
+    Ok(castling)
+}
+
+fn parse_en_passant(field: &str, board: &Board, turn: Player) -> Result<Option<Point>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+
+    let err = || FenError::InvalidEnPassant(field.to_string());
+    let p = square_to_point(field).ok_or_else(err)?;
+
+    // the pawn that just made the double step stands in front of the square
+    let (ep_h, pawn_h) = match turn {
+        Player::White => (2, 3),
+        Player::Black => (5, 4),
+    };
+    let pawn = Some(PieceState { owner: turn.enemy(), piece: ChessPiece::Pawn });
+    if p.h != ep_h || board.get(&Point { h: pawn_h, w: p.w }) != pawn || board.get(&p).is_some() {
+        return Err(err());
+    }
+
+    Ok(Some(p))
+}
+
+impl Position {
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let board = parse_board(fields[0])?;
+
+        for player in [Player::White, Player::Black] {
+            let king = PieceState { owner: player, piece: ChessPiece::King };
+            let count = board.bitboard(king.owner, king.piece).count_ones() as usize;
+            if count != 1 {
+                return Err(FenError::KingCount(player, count));
+            }
+        }
+
+        // ranks 8 and 1 are the first and last byte
+        if board.pieces[ChessPiece::Pawn as usize] & 0xff00_0000_0000_00ff != 0 {
+            return Err(FenError::PawnOnBackRank);
+        }
+
+        let turn = match fields[1] {
+            "w" => Player::White,
+            "b" => Player::Black,
+            s => return Err(FenError::InvalidTurn(s.to_string())),
+        };
+
+        if is_checked(&board, turn.enemy()) {
+            return Err(FenError::EnemyInCheck);
+        }
+
+        let castling = parse_castling(fields[2], &board)?;
+        let en_passant = parse_en_passant(fields[3], &board, turn)?;
+
+        let (halfmove_clock, fullmove_number) = match fields.len() {
+            6 => {
+                let halfmove = fields[4].parse::<u32>()
+                    .map_err(|_| FenError::InvalidHalfmove(fields[4].to_string()))?;
+                let fullmove = fields[5].parse::<u32>().ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| FenError::InvalidFullmove(fields[5].to_string()))?;
+                (halfmove, fullmove)
+            },
+            _ => (0, 1),
+        };
+
+        Ok(Position { board, turn, en_passant, castling, halfmove_clock, fullmove_number })
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut ranks: Vec<String> = Vec::new();
+        for h in 0..CHESS_LEHGT {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for w in 0..CHESS_LEHGT {
+                match &self.board.get(&Point { h, w }) {
+                    Some(x) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank.push(piece_to_char(x));
+                    },
+                    None => { empty += 1; },
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+
+        let turn = match self.turn {
+            Player::White => "w",
+            Player::Black => "b",
+        };
+
+        let castling: String = self.castling.rights().iter()
+            .zip(['K', 'Q', 'k', 'q'])
+            .filter(|(right, _)| **right)
+            .map(|(_, c)| c)
+            .collect();
+        let castling = if castling.is_empty() { String::from("-") } else { castling };
+
+        let en_passant = match &self.en_passant {
+            Some(p) => point_to_square(p),
+            None => String::from("-"),
+        };
+
+        format!("{} {} {} {} {} {}", ranks.join("/"), turn, castling, en_passant, self.halfmove_clock, self.fullmove_number)
+    }
+}
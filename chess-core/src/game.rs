@@ -0,0 +1,48 @@
+use crate::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinReason {
+    Checkmate,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    FivefoldRepetition,
+    SeventyFiveMoveRule,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    // the flag fell but the opponent could not have mated
+    TimeoutVsInsufficientMaterial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    Win(Player, WinReason),
+    Draw(DrawReason),
+}
+
+impl GameResult {
+    pub fn title(&self) -> String {
+        match self {
+            GameResult::Win(player, _) => format!("{} wins", player.name()),
+            GameResult::Draw(_) => String::from("Draw"),
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            GameResult::Win(_, WinReason::Checkmate) => "by checkmate",
+            GameResult::Win(_, WinReason::Timeout) => "on time",
+            GameResult::Draw(DrawReason::Stalemate) => "by stalemate",
+            GameResult::Draw(DrawReason::InsufficientMaterial) => "by insufficient material",
+            GameResult::Draw(DrawReason::FivefoldRepetition) => "by fivefold repetition",
+            GameResult::Draw(DrawReason::SeventyFiveMoveRule) => "by the 75-move rule",
+            GameResult::Draw(DrawReason::ThreefoldRepetition) => "by threefold repetition",
+            GameResult::Draw(DrawReason::FiftyMoveRule) => "by the 50-move rule",
+            GameResult::Draw(DrawReason::TimeoutVsInsufficientMaterial) => "by timeout vs insufficient material",
+        }
+    }
+}
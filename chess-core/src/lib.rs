@@ -0,0 +1,25 @@
+// Chess rules without any GUI: board, move generation, FEN, PGN, perft, the engine
+// and a client for external UCI engines.
+
+mod board;
+mod eval;
+mod game;
+mod movegen;
+mod perft;
+mod position;
+mod search;
+mod zobrist;
+pub mod bitboard;
+pub mod external;
+pub mod fen;
+pub mod pgn;
+
+pub use board::*;
+pub use eval::*;
+pub use game::*;
+pub use movegen::*;
+pub use perft::*;
+pub use position::*;
+pub use search::*;
+
+pub const CHESS_LEHGT: usize = 8;
@@ -0,0 +1,230 @@
+use crate::bitboard::{
+    bishop_attacks, king_attacks, LIGHT_SQUARES, knight_attacks, pawn_attacks, points, queen_attacks, rook_attacks, squares, Bitboard,
+};
+use crate::{Board, Castling, ChessPiece, PieceState, Player, Point, CHESS_LEHGT};
+
+// pseudo-legal targets: the attacked squares minus those holding an own piece
+fn targets(board: &Board, p: &Point, attacks: Bitboard) -> Vec<Point> {
+    let piece = board.get(p).unwrap();
+    points(attacks & !board.colors[piece.owner as usize])
+}
+
+pub fn move_able_rook(board: &Board, p: &Point) -> Vec<Point> {
+    targets(board, p, rook_attacks(p.square(), board.occupied()))
+}
+
+pub fn move_able_bishop(board: &Board, p: &Point) -> Vec<Point> {
+    targets(board, p, bishop_attacks(p.square(), board.occupied()))
+}
+
+pub fn move_able_king(board: &Board, p: &Point) -> Vec<Point> {
+    targets(board, p, king_attacks(p.square()))
+}
+
+pub fn move_able_knight(board: &Board, p: &Point) -> Vec<Point> {
+    targets(board, p, knight_attacks(p.square()))
+}
+
+pub fn move_able_pawn(board: &Board, p: &Point, en_passant: &Option<Point>) -> Vec<Point> {
+    let mut high_light: Vec<Point> = Vec::new();
+
+    let h = p.h;
+    let w = p.w;
+
+    let piece = board.get(p).unwrap();
+    let (dh, home): (i32, usize) = match piece.owner {
+        Player::White => (-1, 6),
+        Player::Black => (1, 1),
+    };
+
+    let one = Point { h: (h as i32 + dh) as usize, w };
+    if board.get(&one).is_none() {
+        high_light.push(one);
+
+        let two = Point { h: (h as i32 + 2 * dh) as usize, w };
+        if h == home && board.get(&two).is_none() {
+            high_light.push(two);
+        }
+    }
+
+    // attck
+    let mut enemies = board.colors[piece.owner.enemy() as usize];
+    if let Some(x) = en_passant {
+        enemies |= 1 << x.square();
+    }
+    high_light.extend(points(pawn_attacks(piece.owner, p.square()) & enemies));
+
+    high_light
+}
+
+pub fn move_able_queen(board: &Board, p: &Point) -> Vec<Point> {
+    targets(board, p, queen_attacks(p.square(), board.occupied()))
+}
+
+// every square attacked by one piece, own pieces included
+pub fn attacks(board: &Board, p: &Point) -> Bitboard {
+    let piece = board.get(p).unwrap();
+    let square = p.square();
+
+    match piece.piece {
+        ChessPiece::Rook    => { rook_attacks(square, board.occupied()) },
+        ChessPiece::Bishop  => { bishop_attacks(square, board.occupied()) },
+        ChessPiece::King    => { king_attacks(square) },
+        ChessPiece::Knight  => { knight_attacks(square) },
+        ChessPiece::Queen   => { queen_attacks(square, board.occupied()) },
+        ChessPiece::Pawn    => { pawn_attacks(piece.owner, square) },
+    }
+}
+
+pub type AttackMap = [[bool; CHESS_LEHGT]; CHESS_LEHGT];
+
+pub fn attack_map(board: &Board, by: Player) -> AttackMap {
+    let mut map: AttackMap = [[false; CHESS_LEHGT]; CHESS_LEHGT];
+
+    let mut attacked: Bitboard = 0;
+    for square in squares(board.colors[by as usize]) {
+        attacked |= attacks(board, &Point::from_square(square));
+    }
+
+    for p in points(attacked) {
+        map[p.h][p.w] = true;
+    }
+
+    map
+}
+
+// looks outwards from the square with each piece's own attack pattern
+pub fn is_attacked(board: &Board, p: &Point, by: Player) -> bool {
+    let square = p.square();
+    let occupied = board.occupied();
+    let queens = board.bitboard(by, ChessPiece::Queen);
+
+    pawn_attacks(by.enemy(), square) & board.bitboard(by, ChessPiece::Pawn) != 0
+        || knight_attacks(square) & board.bitboard(by, ChessPiece::Knight) != 0
+        || king_attacks(square) & board.bitboard(by, ChessPiece::King) != 0
+        || rook_attacks(square, occupied) & (board.bitboard(by, ChessPiece::Rook) | queens) != 0
+        || bishop_attacks(square, occupied) & (board.bitboard(by, ChessPiece::Bishop) | queens) != 0
+}
+
+pub fn move_able_castling(board: &Board, p: &Point, castling: &Castling) -> Vec<Point> {
+    let mut high_light: Vec<Point> = Vec::new();
+
+    let piece = board.get(p).unwrap();
+    let (h, king_moved, r_rook_moved, l_rook_moved, enemy) = match piece.owner {
+        Player::White => (7, castling.w_king, castling.w_r_rook, castling.w_l_rook, Player::Black),
+        Player::Black => (0, castling.b_king, castling.b_r_rook, castling.b_l_rook, Player::White),
+    };
+
+    if king_moved || p.h != h || p.w != 4 || is_attacked(board, p, enemy) {
+        return high_light;
+    }
+
+    let rook = Some(PieceState { owner: piece.owner, piece: ChessPiece::Rook });
+
+    // king side
+    if !r_rook_moved && board.get(&Point { h, w: 7 }) == rook
+        && (5..=6).all(|w| board.get(&Point { h, w }).is_none() && !is_attacked(board, &Point { h, w }, enemy)) {
+        high_light.push(Point { h, w: 6 });
+    }
+
+    // queen side
+    if !l_rook_moved && board.get(&Point { h, w: 0 }) == rook
+        && (1..=3).all(|w| board.get(&Point { h, w }).is_none())
+        && (2..=3).all(|w| !is_attacked(board, &Point { h, w }, enemy)) {
+        high_light.push(Point { h, w: 2 });
+    }
+
+    high_light
+}
+
+pub fn move_able(board: &Board, p: &Point, en_passant: &Option<Point>, castling: &Castling) -> Vec<Point> {
+    let piece = board.get(p).unwrap();
+
+    match piece.piece {
+        ChessPiece::Rook    => { move_able_rook(board, p) },
+        ChessPiece::Bishop  => { move_able_bishop(board, p) },
+        ChessPiece::King    => {
+            let mut high_light = move_able_king(board, p);
+            high_light.extend(move_able_castling(board, p, castling));
+            high_light
+        },
+        ChessPiece::Knight  => { move_able_knight(board, p) },
+        ChessPiece::Queen   => { move_able_queen(board, p) },
+
+        ChessPiece::Pawn    => { move_able_pawn(board, p, en_passant) },
+    }
+}
+
+// plays the move on the board only, including the en passant kill and the castling rook
+pub fn move_piece(board: &mut Board, from: &Point, to: &Point, en_passant: &Option<Point>) {
+    let piece = board.get(from).unwrap();
+
+    // en passant kill
+    if piece.piece == ChessPiece::Pawn && en_passant.as_ref() == Some(to) {
+        board.set(&Point { h: from.h, w: to.w }, None);
+    }
+
+    // castling rook
+    if piece.piece == ChessPiece::King && (to.w as i32 - from.w as i32).abs() == 2 {
+        let (rook_from, rook_to) = if to.w == 6 { (7, 5) } else { (0, 3) };
+        let rook = board.get(&Point { h: to.h, w: rook_from });
+        board.set(&Point { h: to.h, w: rook_to }, rook);
+        board.set(&Point { h: to.h, w: rook_from }, None);
+    }
+
+    board.set(from, None);
+    board.set(to, Some(piece));
+}
+
+pub fn find_king(board: &Board, player: Player) -> Option<Point> {
+    squares(board.bitboard(player, ChessPiece::King)).next().map(Point::from_square)
+}
+
+pub fn is_checked(board: &Board, player: Player) -> bool {
+    match find_king(board, player) {
+        Some(king) => is_attacked(board, &king, player.enemy()),
+        None => false,
+    }
+}
+
+// pseudo-legal moves played on a scratch board, dropping those that leave the own king attacked
+pub fn legal_move_able(board: &Board, p: &Point, en_passant: &Option<Point>, castling: &Castling) -> Vec<Point> {
+    let owner = board.get(p).unwrap().owner;
+
+    move_able(board, p, en_passant, castling)
+        .into_iter()
+        .filter(|to| {
+            let mut scratch = *board;
+            move_piece(&mut scratch, p, to, en_passant);
+            !is_checked(&scratch, owner)
+        })
+        .collect()
+}
+
+pub fn has_legal_move(board: &Board, player: Player, en_passant: &Option<Point>, castling: &Castling) -> bool {
+    squares(board.colors[player as usize])
+        .any(|square| !legal_move_able(board, &Point::from_square(square), en_passant, castling).is_empty())
+}
+
+// dead positions: bare kings plus at most one minor piece, or bishops all on one colour
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let kings = board.pieces[ChessPiece::King as usize];
+    let bishops = board.pieces[ChessPiece::Bishop as usize];
+    let minors = board.pieces[ChessPiece::Knight as usize] | bishops;
+
+    if board.occupied() & !kings & !minors != 0 {
+        return false;
+    }
+
+    minors.count_ones() <= 1
+        || (minors == bishops && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+}
+
+// false when `owner` has only the king, or the king and a single knight or bishop;
+// decides whether running out of time loses or draws
+pub fn has_mating_material(board: &Board, owner: Player) -> bool {
+    let own = board.colors[owner as usize] & !board.pieces[ChessPiece::King as usize];
+    let minors = board.pieces[ChessPiece::Knight as usize] | board.pieces[ChessPiece::Bishop as usize];
+
+    own & !minors != 0 || own.count_ones() > 1
+}
@@ -0,0 +1,39 @@
+use crate::{Move, Position};
+
+// number of leaf nodes of the legal move tree, the standard move generator check
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let unmake = position.make_move(&mv);
+        nodes += perft(position, depth - 1);
+        position.unmake_move(&mv, unmake);
+    }
+
+    nodes
+}
+
+// perft split by the first move, to find which subtree disagrees with a reference engine
+pub fn divide(position: &mut Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    position.legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let unmake = position.make_move(&mv);
+            let nodes = perft(position, depth - 1);
+            position.unmake_move(&mv, unmake);
+            (mv, nodes)
+        })
+        .collect()
+}
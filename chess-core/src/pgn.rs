@@ -0,0 +1,538 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fen::{point_to_square, square_to_point};
+use crate::{
+    ChessPiece, GameResult, Move, PieceState, Player, Point, Position, CHESS_LEHGT,
+    has_legal_move, is_checked, legal_move_able,
+};
+
+pub fn piece_letter(piece: ChessPiece) -> &'static str {
+    match piece {
+        ChessPiece::King => "K",
+        ChessPiece::Queen => "Q",
+        ChessPiece::Rook => "R",
+        ChessPiece::Bishop => "B",
+        ChessPiece::Knight => "N",
+        ChessPiece::Pawn => "",
+    }
+}
+
+// Standard Algebraic Notation of a legal move played from `before`
+pub fn san(before: &Position, mv: &Move) -> String {
+    let (from, to, promotion) = (&mv.from, &mv.to, mv.promotion);
+    let piece = before.board.get(from).unwrap();
+    let mut san = String::new();
+
+    if piece.piece == ChessPiece::King && (to.w as i32 - from.w as i32).abs() == 2 {
+        san.push_str(if to.w == 6 { "O-O" } else { "O-O-O" });
+    } else {
+        let capture = before.board.get(to).is_some()
+            || (piece.piece == ChessPiece::Pawn && before.en_passant.as_ref() == Some(to));
+        let square = point_to_square(from);
+
+        if piece.piece == ChessPiece::Pawn {
+            if capture {
+                san.push_str(&square[..1]);
+            }
+        } else {
+            san.push_str(piece_letter(piece.piece));
+
+            // other pieces of the same kind that could also reach the square
+            let mut rivals: Vec<Point> = Vec::new();
+            for h in 0..CHESS_LEHGT {
+                for w in 0..CHESS_LEHGT {
+                    let p = Point { h, w };
+                    if p != *from && before.board.get(&p) == Some(piece)
+                        && legal_move_able(&before.board, &p, &before.en_passant, &before.castling).contains(to) {
+                        rivals.push(p);
+                    }
+                }
+            }
+
+            if !rivals.is_empty() {
+                if rivals.iter().all(|p| p.w != from.w) {
+                    san.push_str(&square[..1]);
+                } else if rivals.iter().all(|p| p.h != from.h) {
+                    san.push_str(&square[1..]);
+                } else {
+                    san.push_str(&square);
+                }
+            }
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&point_to_square(to));
+
+        if let Some(promotion) = promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+    }
+
+    let mut after = before.clone();
+    after.make_move(mv);
+    if is_checked(&after.board, after.turn) {
+        if has_legal_move(&after.board, after.turn, &after.en_passant, &after.castling) {
+            san.push('+');
+        } else {
+            san.push('#');
+        }
+    }
+
+    san
+}
+
+pub fn result_tag(result: &Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::Win(Player::White, _)) => "1-0",
+        Some(GameResult::Win(Player::Black, _)) => "0-1",
+        Some(GameResult::Draw(_)) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+// days since 1970-01-01 to a civil date
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86400).unwrap_or(0) as i64;
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// `names` are White's and Black's
+pub fn export(start: &Position, moves: &[String], result: &Option<GameResult>, names: [&str; 2]) -> String {
+    let result = result_tag(result);
+
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", String::from("Casual game")),
+        ("Site", String::from("?")),
+        ("Date", today()),
+        ("Round", String::from("-")),
+        ("White", String::from(names[0])),
+        ("Black", String::from(names[1])),
+        ("Result", String::from(result)),
+    ] {
+        // names are free text, and PGN escapes a backslash or quote inside a tag value
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+
+    let start_fen = start.to_fen();
+    if start_fen != Position::new().to_fen() {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", start_fen));
+    }
+    pgn.push('\n');
+
+    let mut tokens: Vec<String> = Vec::new();
+    let mut number = start.fullmove_number;
+    let mut turn = start.turn;
+    for (i, san) in moves.iter().enumerate() {
+        match turn {
+            Player::White => { tokens.push(format!("{}.", number)); },
+            Player::Black if i == 0 => { tokens.push(format!("{}...", number)); },
+            Player::Black => {},
+        }
+        tokens.push(san.clone());
+
+        if turn == Player::Black {
+            number += 1;
+        }
+        turn = turn.enemy();
+    }
+    tokens.push(String::from(result));
+
+    // keep movetext lines under 80 characters
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > 79 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+
+    pgn
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    fn new() -> Self {
+        PgnGame { tags: Vec::new(), moves: Vec::new(), result: String::from("*") }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // "game 2 (Kasparov vs Topalov)"
+    pub fn title(&self, index: usize) -> String {
+        format!("game {} ({} vs {})", index + 1, self.tag("White").unwrap_or("?"), self.tag("Black").unwrap_or("?"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnError {
+    pub game: String,
+    pub ply: Option<(usize, String)>,
+    pub message: String,
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.ply {
+            Some((ply, san)) => write!(f, "{}, ply {} ({}): {}", self.game, ply, san, self.message),
+            None => write!(f, "{}: {}", self.game, self.message),
+        }
+    }
+}
+
+// splits a PGN file into games, keeping only the mainline moves
+pub fn parse_games(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games: Vec<PgnGame> = Vec::new();
+    let mut game = PgnGame::new();
+    let mut in_movetext = false;
+    let mut variation = 0;
+
+    let error = |games: &Vec<PgnGame>, message: &str| PgnError {
+        game: format!("game {}", games.len() + 1),
+        ply: None,
+        message: String::from(message),
+    };
+
+    let chars: Vec<char> = pgn.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '%' && (i == 0 || chars[i - 1] == '\n') {
+            // escape line
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == ';' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '{' {
+            while i < chars.len() && chars[i] != '}' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(error(&games, "unterminated comment"));
+            }
+            i += 1;
+        } else if c == '(' {
+            variation += 1;
+            i += 1;
+        } else if c == ')' {
+            if variation == 0 {
+                return Err(error(&games, "unmatched ')'"));
+            }
+            variation -= 1;
+            i += 1;
+        } else if c == '[' && variation == 0 {
+            // a tag after movetext without a result starts the next game
+            if in_movetext {
+                games.push(game);
+                game = PgnGame::new();
+                in_movetext = false;
+            }
+
+            let start = i + 1;
+            while i < chars.len() && chars[i] != '"' && chars[i] != ']' {
+                i += 1;
+            }
+            let name: String = chars[start..i.min(chars.len())].iter().collect::<String>().trim().to_string();
+
+            let mut value = String::new();
+            if i < chars.len() && chars[i] == '"' {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                    }
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                i += 1;
+            }
+            while i < chars.len() && chars[i] != ']' {
+                i += 1;
+            }
+            if i >= chars.len() || name.is_empty() {
+                return Err(error(&games, "malformed tag pair"));
+            }
+            i += 1;
+
+            game.tags.push((name, value));
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !"{}()[];".contains(chars[i]) {
+                i += 1;
+            }
+            if i == start {
+                i += 1;
+                continue;
+            }
+            if variation > 0 {
+                continue;
+            }
+
+            let token: String = chars[start..i].iter().collect();
+            in_movetext = true;
+
+            if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                game.result = token;
+                games.push(game);
+                game = PgnGame::new();
+                in_movetext = false;
+                continue;
+            }
+
+            // NAGs and move numbers such as "12." or "12...", possibly glued to the move
+            if token.starts_with('$') {
+                continue;
+            }
+            // digits alone are not a move number, "0-0" castles
+            let number = token.trim_start_matches(|c: char| c.is_ascii_digit());
+            let san = match number.starts_with('.') {
+                true => number.trim_start_matches('.'),
+                false => token.as_str(),
+            };
+            let san = san.trim_end_matches(['!', '?']);
+            if !san.is_empty() {
+                game.moves.push(san.to_string());
+            }
+        }
+    }
+
+    if variation > 0 {
+        return Err(error(&games, "unterminated variation"));
+    }
+    if in_movetext || !game.tags.is_empty() {
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+// resolves a SAN token against the legal moves of the position
+pub fn parse_san(position: &Position, san: &str) -> Result<Move, String> {
+    let s = san.trim_end_matches(['+', '#', '!', '?']);
+    let turn = position.turn;
+
+    let mut candidates: Vec<(Point, Point)> = Vec::new();
+    let mut promotion = None;
+
+    if matches!(s, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+        let h = if turn == Player::White { 7 } else { 0 };
+        let to = Point { h, w: if s.len() == 3 { 6 } else { 2 } };
+        let from = Point { h, w: 4 };
+        if position.board.get(&from) == Some(PieceState { owner: turn, piece: ChessPiece::King }) {
+            candidates.push((from, to));
+        }
+    } else {
+        let mut body: Vec<char> = s.chars().filter(|c| !matches!(c, 'x' | ':' | '-')).collect();
+
+        let piece = match body.first() {
+            Some('K') => ChessPiece::King,
+            Some('Q') => ChessPiece::Queen,
+            Some('R') => ChessPiece::Rook,
+            Some('B') => ChessPiece::Bishop,
+            Some('N') => ChessPiece::Knight,
+            _ => ChessPiece::Pawn,
+        };
+        if piece != ChessPiece::Pawn {
+            body.remove(0);
+        }
+
+        // "e8=Q" or "e8Q"
+        if let Some(last) = body.last().copied() {
+            let promoted = match last {
+                'Q' => Some(ChessPiece::Queen),
+                'R' => Some(ChessPiece::Rook),
+                'B' => Some(ChessPiece::Bishop),
+                'N' => Some(ChessPiece::Knight),
+                _ => None,
+            };
+            if promoted.is_some() {
+                body.pop();
+                if body.last() == Some(&'=') {
+                    body.pop();
+                }
+                promotion = promoted;
+            }
+        }
+
+        if body.len() < 2 {
+            return Err(String::from("not a move"));
+        }
+        let square: String = body[body.len() - 2..].iter().collect();
+        let to = square_to_point(&square).ok_or_else(|| String::from("not a move"))?;
+        let hints = &body[..body.len() - 2];
+        if hints.len() > 2 || hints.iter().any(|c| !matches!(c, 'a'..='h' | '1'..='8')) {
+            return Err(String::from("not a move"));
+        }
+
+        for h in 0..CHESS_LEHGT {
+            for w in 0..CHESS_LEHGT {
+                let from = Point { h, w };
+                let square = point_to_square(&from);
+                if position.board.get(&from) == Some(PieceState { owner: turn, piece })
+                    && hints.iter().all(|c| square.contains(*c)) {
+                    candidates.push((from, to.clone()));
+                }
+            }
+        }
+    }
+
+    let legal: Vec<(Point, Point)> = candidates.into_iter()
+        .filter(|(from, to)| legal_move_able(&position.board, from, &position.en_passant, &position.castling).contains(to))
+        .collect();
+
+    match legal.as_slice() {
+        [] => Err(String::from("no legal move matches")),
+        [(from, to)] => {
+            let piece = position.board.get(from).unwrap().piece;
+            let last_rank = to.h == 0 || to.h == CHESS_LEHGT - 1;
+            match (piece == ChessPiece::Pawn && last_rank, promotion) {
+                (true, None) => Err(String::from("promotion piece missing")),
+                (false, Some(_)) => Err(String::from("only pawns on the last rank promote")),
+                _ => Ok(Move { from: from.clone(), to: to.clone(), promotion }),
+            }
+        },
+        _ => Err(format!(
+            "ambiguous, could be played from {}",
+            legal.iter().map(|(from, _)| point_to_square(from)).collect::<Vec<String>>().join(" or ")
+        )),
+    }
+}
+
+// the starting position and the resolved mainline of a game
+pub fn replay(game: &PgnGame, index: usize) -> Result<(Position, Vec<Move>), PgnError> {
+    let error = |ply: Option<(usize, String)>, message: String| PgnError { game: game.title(index), ply, message };
+
+    let start = match game.tag("FEN") {
+        Some(fen) => Position::from_fen(fen).map_err(|e| error(None, format!("invalid FEN tag: {}", e)))?,
+        None => Position::new(),
+    };
+
+    let mut position = start.clone();
+    let mut moves: Vec<Move> = Vec::new();
+    for (i, san) in game.moves.iter().enumerate() {
+        let mv = parse_san(&position, san)
+            .map_err(|e| error(Some((i + 1, san.clone())), e))?;
+        position.make_move(&mv);
+        moves.push(mv);
+    }
+
+    Ok((start, moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mainline(pgn: &str) -> Vec<String> {
+        let games = parse_games(pgn).unwrap();
+        assert_eq!(games.len(), 1);
+        let (_, moves) = replay(&games[0], 0).unwrap();
+        moves.iter().map(|mv| mv.uci()).collect()
+    }
+
+    #[test]
+    fn castling_with_letters_and_zeros() {
+        let castled = ["e1g1", "e8c8"];
+        let before = "1. e4 d5 2. Nf3 Qd6 3. Bc4 Bd7 4. d3 Nc6";
+        for (short, long) in [("O-O", "O-O-O"), ("0-0", "0-0-0"), ("O-O+", "0-0-0!?")] {
+            let moves = mainline(&format!("{} 5. {} {} *", before, short, long));
+            assert_eq!(moves[8..], castled, "{} {}", short, long);
+        }
+
+        let moves = mainline("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 *");
+        assert_eq!(moves[6], "e1g1");
+    }
+
+    #[test]
+    fn move_numbers_glued_to_moves() {
+        let moves = mainline("1.e4 e5 2.Nf3 2...Nc6 3.Bb5 *");
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]);
+    }
+
+    #[test]
+    fn comments_variations_and_nags_are_skipped() {
+        let pgn = "1. e4 {best by test} e5 ; the classical reply\n\
+                   2. Nf3 $1 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6!? 3. Bb5 $14 *";
+        assert_eq!(mainline(pgn), ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]);
+    }
+
+    #[test]
+    fn games_are_split_on_results_and_tags() {
+        let pgn = "[White \"A\"]\n\n1. e4 e5 1-0\n\n[White \"B\"]\n\n1. d4 d5\n\n[White \"C\"]\n\n1. c4 1/2-1/2\n";
+        let games = parse_games(pgn).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("White"), Some("A"));
+        assert_eq!(games[0].result, "1-0");
+        assert_eq!(games[1].moves, ["d4", "d5"]);
+        assert_eq!(games[1].result, "*");
+        assert_eq!(games[2].result, "1/2-1/2");
+    }
+
+    #[test]
+    fn ambiguous_moves_are_rejected() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/1N3NK1 w - - 0 1").unwrap();
+        let error = parse_san(&position, "Nd2").unwrap_err();
+        assert!(error.starts_with("ambiguous"), "{}", error);
+
+        let mv = parse_san(&position, "Nbd2").unwrap();
+        assert_eq!(mv.uci(), "b1d2");
+    }
+
+    #[test]
+    fn broken_movetext_is_reported() {
+        let error = parse_games("1. e4 (1. d4 *").unwrap_err();
+        assert_eq!(error.message, "unterminated variation");
+
+        let games = parse_games("1. e4 e5 2. Ke3 *").unwrap();
+        let error = replay(&games[0], 0).unwrap_err();
+        assert_eq!(error.ply, Some((3, String::from("Ke3"))));
+    }
+
+    #[test]
+    fn exported_names_are_escaped() {
+        let names = ["Anna \"The Rook\"", "C:\\Black"];
+        let pgn = export(&Position::new(), &[String::from("e4")], &None, names);
+        assert!(pgn.contains(r#"[White "Anna \"The Rook\""]"#), "{}", pgn);
+
+        let games = parse_games(&pgn).unwrap();
+        assert_eq!(games[0].tag("White"), Some(names[0]));
+        assert_eq!(games[0].tag("Black"), Some(names[1]));
+        assert_eq!(games[0].moves, ["e4"]);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::bitboard::{pawn_attacks, squares};
+use crate::fen::point_to_square;
+use crate::{
+    zobrist, Board, Castling, ChessPiece, PieceState, Player, Point, CHESS_LEHGT,
+    has_legal_move, init_board, is_checked, legal_move_able, move_piece,
+};
+
+// everything a FEN string describes
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub board: Board,
+    pub turn: Player,
+    pub en_passant: Option<Point>,
+    pub castling: Castling,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+// Zobrist hash of what makes two positions the same for the repetition rules
+pub type PositionKey = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Point,
+    pub to: Point,
+    pub promotion: Option<ChessPiece>,
+}
+
+impl Move {
+    // long algebraic coordinates as UCI uses them, "e2e4" or "e7e8q"
+    pub fn uci(&self) -> String {
+        let promotion = match self.promotion {
+            Some(ChessPiece::Queen) => "q",
+            Some(ChessPiece::Rook) => "r",
+            Some(ChessPiece::Bishop) => "b",
+            Some(ChessPiece::Knight) => "n",
+            _ => "",
+        };
+        format!("{}{}{}", point_to_square(&self.from), point_to_square(&self.to), promotion)
+    }
+}
+
+// what make_move hands back so the move can be taken back
+#[derive(Debug, Clone)]
+pub struct Unmake {
+    moved: PieceState,
+    captured: Option<(Point, PieceState)>,
+    en_passant: Option<Point>,
+    castling: Castling,
+    halfmove_clock: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Position {
+    pub fn new() -> Self {
+        Position {
+            board: init_board(),
+            turn: Player::White,
+            en_passant: None,
+            castling: Castling::new(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+
+        for square in squares(self.board.colors[self.turn as usize]) {
+            let from = Point::from_square(square);
+            let piece = self.board.get(&from).unwrap().piece;
+
+            for to in legal_move_able(&self.board, &from, &self.en_passant, &self.castling) {
+                if piece == ChessPiece::Pawn && (to.h == 0 || to.h == CHESS_LEHGT - 1) {
+                    for promotion in [ChessPiece::Queen, ChessPiece::Rook, ChessPiece::Bishop, ChessPiece::Knight] {
+                        moves.push(Move { from: from.clone(), to: to.clone(), promotion: Some(promotion) });
+                    }
+                } else {
+                    moves.push(Move { from: from.clone(), to, promotion: None });
+                }
+            }
+        }
+
+        moves
+    }
+
+    // plays a legal move with all the bookkeeping; without a promotion piece the pawn stays on the last rank
+    pub fn make_move(&mut self, mv: &Move) -> Unmake {
+        let (from, to) = (&mv.from, &mv.to);
+        let piece_state = self.board.get(from).unwrap();
+
+        let captured = match self.board.get(to) {
+            Some(x) => Some((to.clone(), x)),
+            None if piece_state.piece == ChessPiece::Pawn && self.en_passant.as_ref() == Some(to) => {
+                Some((Point { h: from.h, w: to.w }, PieceState { owner: piece_state.owner.enemy(), piece: ChessPiece::Pawn }))
+            },
+            None => None,
+        };
+        let unmake = Unmake {
+            moved: piece_state,
+            captured: captured.clone(),
+            en_passant: self.en_passant.clone(),
+            castling: self.castling,
+            halfmove_clock: self.halfmove_clock,
+        };
+
+        // fifty-move rule clock
+        if piece_state.piece == ChessPiece::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        // move
+        move_piece(&mut self.board, from, to, &self.en_passant);
+        self.castling.update(from, to);
+
+        if let Some(piece) = mv.promotion {
+            self.board.set(to, Some(PieceState { owner: piece_state.owner, piece }));
+        }
+
+        // en passant able
+        self.en_passant = None;
+        if piece_state.piece == ChessPiece::Pawn && ((to.h as i32 - from.h as i32).abs() == 2) && (to.w == from.w) {
+            if piece_state.owner == Player::White && to.h == 4 {
+                self.en_passant = Some(Point {h: to.h+1, w: to.w});
+            } else if piece_state.owner == Player::Black && to.h == 3 {
+                self.en_passant = Some(Point {h: to.h-1, w: to.w});
+            }
+        }
+
+        if self.turn == Player::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.enemy();
+
+        unmake
+    }
+
+    pub fn unmake_move(&mut self, mv: &Move, unmake: Unmake) {
+        let (from, to) = (&mv.from, &mv.to);
+
+        self.turn = self.turn.enemy();
+        if self.turn == Player::Black {
+            self.fullmove_number -= 1;
+        }
+
+        self.board.set(to, None);
+        self.board.set(from, Some(unmake.moved));
+
+        // castling rook
+        if unmake.moved.piece == ChessPiece::King && (to.w as i32 - from.w as i32).abs() == 2 {
+            let (rook_from, rook_to) = if to.w == 6 { (7, 5) } else { (0, 3) };
+            let rook = self.board.get(&Point { h: to.h, w: rook_to });
+            self.board.set(&Point { h: to.h, w: rook_from }, rook);
+            self.board.set(&Point { h: to.h, w: rook_to }, None);
+        }
+
+        if let Some((p, piece)) = unmake.captured {
+            self.board.set(&p, Some(piece));
+        }
+
+        self.en_passant = unmake.en_passant;
+        self.castling = unmake.castling;
+        self.halfmove_clock = unmake.halfmove_clock;
+    }
+
+    // the legal move written as UCI coordinates, e.g. "e1g1" for white short castling
+    pub fn uci_move(&self, uci: &str) -> Option<Move> {
+        self.legal_moves().into_iter().find(|mv| mv.uci() == uci)
+    }
+
+    pub fn is_check(&self) -> bool {
+        is_checked(&self.board, self.turn)
+    }
+
+    pub fn has_legal_move(&self) -> bool {
+        has_legal_move(&self.board, self.turn, &self.en_passant, &self.castling)
+    }
+
+    // Zobrist hash of the whole position, en passant file included whenever the square is set
+    pub fn hash(&self) -> u64 {
+        self.hash_with(self.en_passant.as_ref())
+    }
+
+    pub fn key(&self) -> PositionKey {
+        // the en passant square only matters when the capture is actually playable
+        let en_passant = self.en_passant.as_ref().filter(|ep| {
+            squares(pawn_attacks(self.turn.enemy(), ep.square()) & self.board.bitboard(self.turn, ChessPiece::Pawn))
+                .any(|square| legal_move_able(&self.board, &Point::from_square(square), &self.en_passant, &self.castling).contains(ep))
+        });
+
+        self.hash_with(en_passant)
+    }
+
+    fn hash_with(&self, en_passant: Option<&Point>) -> u64 {
+        let mut hash = self.board.hash() ^ zobrist::castling(self.castling.rights());
+        if self.turn == Player::Black {
+            hash ^= zobrist::black_to_move();
+        }
+        if let Some(ep) = en_passant {
+            hash ^= zobrist::en_passant_file(ep.w);
+        }
+        hash
+    }
+}
@@ -0,0 +1,505 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{evaluate, is_insufficient_material, piece_value, ChessPiece, Move, Position, PAWN_VALUE};
+
+// scores are centipawns from the side to move; mates count down from MATE by the ply they happen at
+pub const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+const MAX_PLY: usize = 64;
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_SKILL_LEVEL: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Cp(i32),
+    // moves until mate, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    fn from_value(value: i32) -> Score {
+        if value.abs() >= MATE - MAX_PLY as i32 {
+            let moves = (MATE - value.abs() + 1) / 2;
+            Score::Mate(if value > 0 { moves } else { -moves })
+        } else {
+            Score::Cp(value)
+        }
+    }
+}
+
+// when to stop thinking; without any limit the search runs until stopped
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+}
+
+// reported for every line of every finished iteration
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    // 1 for the best line, 2 for the second best, and so on
+    pub multipv: usize,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    key: u64,
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best: Option<Move>,
+}
+
+// iterative-deepening alpha-beta; keeps its transposition table between searches
+pub struct Engine {
+    table: Vec<Option<Entry>>,
+    killers: Vec<[Option<Move>; 2]>,
+    history: [[i32; 64]; 64],
+    stop: Arc<AtomicBool>,
+    aborted: bool,
+    nodes: u64,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    root_depth: u32,
+    // root moves already shown as better lines in this iteration
+    excluded: Vec<Move>,
+    multi_pv: usize,
+    skill_level: u32,
+    rng: u64,
+    // hashes of the game so far and of the line being searched, for repetitions
+    path: Vec<u64>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_capture(position: &Position, mv: &Move) -> bool {
+    position.board.get(&mv.to).is_some()
+        || (position.en_passant.as_ref() == Some(&mv.to) && position.board.get(&mv.from).map(|x| x.piece) == Some(ChessPiece::Pawn))
+}
+
+// most valuable victim first, cheapest attacker first among equal victims
+fn mvv_lva(position: &Position, mv: &Move) -> i32 {
+    let victim = position.board.get(&mv.to).map(|x| x.piece).unwrap_or(ChessPiece::Pawn);
+    let attacker = position.board.get(&mv.from).unwrap().piece;
+    10 * piece_value(victim) - piece_value(attacker) / 10
+}
+
+// mate scores are stored relative to the node, not the root
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -(MATE - MAX_PLY as i32) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -(MATE - MAX_PLY as i32) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn table_entries(megabytes: usize) -> usize {
+    (megabytes.max(1) << 20) / std::mem::size_of::<Option<Entry>>()
+}
+
+// xorshift64, seeded from the clock
+fn seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or(0);
+    nanos | 1
+}
+
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            table: vec![None; table_entries(DEFAULT_HASH_MB)],
+            killers: vec![[None, None]; MAX_PLY + 1],
+            history: [[0; 64]; 64],
+            stop: Arc::new(AtomicBool::new(false)),
+            aborted: false,
+            nodes: 0,
+            deadline: None,
+            node_limit: None,
+            root_depth: 0,
+            excluded: Vec::new(),
+            multi_pv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            rng: seed(),
+            path: Vec::new(),
+        }
+    }
+
+    // number of best lines to search and report
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    // below MAX_SKILL_LEVEL the engine searches shallower and sometimes picks a worse line on purpose
+    pub fn set_skill_level(&mut self, level: u32) {
+        self.skill_level = level.min(MAX_SKILL_LEVEL);
+    }
+
+    // transposition table size, which also empties it
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.table = vec![None; table_entries(megabytes)];
+    }
+
+    // forgets everything learned from earlier searches, for a new game
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+        self.history = [[0; 64]; 64];
+    }
+
+    // `history` holds the hashes of the positions before this one, for the repetition rule;
+    // setting `stop` from another thread ends the search
+    pub fn search(
+        &mut self,
+        position: &Position,
+        history: &[u64],
+        limits: &Limits,
+        stop: &Arc<AtomicBool>,
+        mut on_info: impl FnMut(&SearchInfo),
+    ) -> Option<Move> {
+        let start = Instant::now();
+        let mut position = position.clone();
+
+        let root_moves = position.legal_moves();
+        let mut best = root_moves.first()?.clone();
+
+        self.stop = stop.clone();
+        self.aborted = false;
+        self.nodes = 0;
+        self.deadline = limits.movetime.map(|time| start + time);
+        self.node_limit = limits.nodes;
+        self.path = history.to_vec();
+        self.killers = vec![[None, None]; MAX_PLY + 1];
+        for row in self.history.iter_mut() {
+            for value in row.iter_mut() {
+                *value /= 8;
+            }
+        }
+
+        let weakened = self.skill_level < MAX_SKILL_LEVEL;
+        let mut max_depth = limits.depth.unwrap_or(MAX_PLY as u32).clamp(1, MAX_PLY as u32);
+        let mut multi_pv = self.multi_pv;
+        if weakened {
+            max_depth = max_depth.min(1 + self.skill_level / 2);
+            multi_pv = multi_pv.max(4);
+        }
+        let multi_pv = multi_pv.min(root_moves.len());
+
+        let mut lines: Vec<(i32, Vec<Move>)> = Vec::new();
+        'deepening: for depth in 1..=max_depth {
+            self.root_depth = depth;
+
+            // each line searches the root without the moves of the better lines
+            let mut iteration: Vec<(i32, Vec<Move>)> = Vec::new();
+            self.excluded = Vec::new();
+            for _ in 0..multi_pv {
+                let mut pv = Vec::new();
+                let score = self.negamax(&mut position, depth, 0, -INFINITY, INFINITY, &mut pv);
+                if self.aborted {
+                    break 'deepening;
+                }
+                self.excluded.push(pv[0].clone());
+                self.extend_from_table(&mut position, &mut pv, depth as usize);
+                iteration.push((score, pv));
+            }
+            iteration.sort_by_key(|(score, _)| -score);
+            lines = iteration;
+
+            for (i, (score, pv)) in lines.iter().enumerate() {
+                on_info(&SearchInfo {
+                    depth,
+                    multipv: i + 1,
+                    score: Score::from_value(*score),
+                    nodes: self.nodes,
+                    time: start.elapsed(),
+                    pv: pv.clone(),
+                });
+            }
+
+            // a forced mate inside the horizon will not get any shorter
+            if lines[0].0.abs() >= MATE - depth as i32 {
+                break;
+            }
+            // the next iteration takes several times longer, so do not start what cannot finish
+            if let Some(movetime) = limits.movetime {
+                if start.elapsed() * 2 > movetime {
+                    break;
+                }
+            }
+        }
+        self.excluded = Vec::new();
+
+        if weakened {
+            best = self.pick_weaker(&lines);
+        } else if let Some((_, pv)) = lines.first() {
+            best = pv[0].clone();
+        }
+
+        Some(best)
+    }
+
+    // the same idea as Stockfish's skill level: every line gets a random bonus that grows
+    // with the weakness, so worse moves win more often at lower levels
+    fn pick_weaker(&mut self, lines: &[(i32, Vec<Move>)]) -> Move {
+        let weakness = 120 - 2 * self.skill_level as i32;
+        let top = lines[0].0;
+        let deviation = (top - lines[lines.len() - 1].0).min(PAWN_VALUE);
+
+        let mut best = (-INFINITY, &lines[0].1[0]);
+        for (score, pv) in lines {
+            let random = (next_random(&mut self.rng) % weakness as u64) as i32;
+            let push = (weakness * (top - score) + deviation * random) / 128;
+            if score + push > best.0 {
+                best = (score + push, &pv[0]);
+            }
+        }
+
+        best.1.clone()
+    }
+
+    // table cutoffs cut the line short; the stored best moves usually know how it goes on
+    fn extend_from_table(&self, position: &mut Position, pv: &mut Vec<Move>, length: usize) {
+        let mut played = Vec::new();
+        for mv in pv.iter() {
+            played.push((mv.clone(), position.make_move(mv)));
+        }
+
+        while pv.len() < length {
+            let hash = position.hash();
+            let next = match &self.table[hash as usize % self.table.len()] {
+                Some(entry) if entry.key == hash => entry.best.clone(),
+                _ => None,
+            };
+            let Some(mv) = next.filter(|mv| position.legal_moves().contains(mv)) else {
+                break;
+            };
+
+            played.push((mv.clone(), position.make_move(&mv)));
+            pv.push(mv);
+        }
+
+        for (mv, unmake) in played.into_iter().rev() {
+            position.unmake_move(&mv, unmake);
+        }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        // the first iteration always finishes so there is a move to play
+        if self.root_depth > 1 {
+            if self.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+            if self.nodes.is_multiple_of(1024) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.aborted = true;
+            }
+            if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+                self.aborted = true;
+            }
+        }
+        self.aborted
+    }
+
+    fn order(&self, position: &Position, moves: &mut [Move], best: &Option<Move>, ply: usize) {
+        moves.sort_by_cached_key(|mv| {
+            let score = if best.as_ref() == Some(mv) {
+                1_000_000
+            } else if is_capture(position, mv) {
+                100_000 + mvv_lva(position, mv)
+            } else if let Some(piece) = mv.promotion {
+                90_000 + piece_value(piece)
+            } else if self.killers[ply][0].as_ref() == Some(mv) {
+                80_000
+            } else if self.killers[ply][1].as_ref() == Some(mv) {
+                79_000
+            } else {
+                self.history[mv.from.square()][mv.to.square()]
+            };
+            -score
+        });
+    }
+
+    fn negamax(&mut self, position: &mut Position, depth: u32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let hash = position.hash();
+        if ply > 0 {
+            if position.halfmove_clock >= 100 || is_insufficient_material(&position.board) || self.path.contains(&hash) {
+                return 0;
+            }
+            if ply >= MAX_PLY {
+                return evaluate(position);
+            }
+        }
+
+        let in_check = position.is_check();
+        let depth = if in_check { depth + 1 } else { depth };
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        let mut table_move = None;
+        if let Some(entry) = &self.table[hash as usize % self.table.len()] {
+            if entry.key == hash {
+                table_move = entry.best.clone();
+                let score = from_table(entry.score, ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if ply > 0 && entry.depth >= depth && cutoff {
+                    return score;
+                }
+            }
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order(position, &mut moves, &table_move, ply);
+
+        let alpha_start = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+
+        self.path.push(hash);
+        for mv in moves {
+            if ply == 0 && self.excluded.contains(&mv) {
+                continue;
+            }
+
+            let capture = is_capture(position, &mv);
+
+            let unmake = position.make_move(&mv);
+            let mut line = Vec::new();
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            position.unmake_move(&mv, unmake);
+
+            if self.aborted {
+                self.path.pop();
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv.clone());
+
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(mv.clone());
+                    pv.extend(line);
+                }
+            }
+
+            if alpha >= beta {
+                // quiet moves that refute a line are worth trying early elsewhere
+                if !capture && mv.promotion.is_none() {
+                    if self.killers[ply][0].as_ref() != Some(&mv) {
+                        self.killers[ply][1] = self.killers[ply][0].take();
+                        self.killers[ply][0] = Some(mv.clone());
+                    }
+                    let history = &mut self.history[mv.from.square()][mv.to.square()];
+                    *history = (*history + (depth * depth) as i32).min(50_000);
+                }
+                break;
+            }
+        }
+        self.path.pop();
+
+        let bound = if best_score <= alpha_start {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        // a root searched without some of its moves is not the real position
+        if ply == 0 && !self.excluded.is_empty() {
+            return best_score;
+        }
+        let index = hash as usize % self.table.len();
+        self.table[index] = Some(Entry { key: hash, depth, score: to_table(best_score, ply), bound, best: best_move });
+
+        best_score
+    }
+
+    // only captures and promotions, until the position is quiet
+    fn quiescence(&mut self, position: &mut Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let stand_pat = evaluate(position);
+        if ply >= MAX_PLY || stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = position.legal_moves()
+            .into_iter()
+            .filter(|mv| is_capture(position, mv) || mv.promotion == Some(ChessPiece::Queen))
+            .collect();
+        moves.sort_by_cached_key(|mv| -mvv_lva(position, mv));
+
+        for mv in moves {
+            let unmake = position.make_move(&mv);
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha);
+            position.unmake_move(&mv, unmake);
+
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
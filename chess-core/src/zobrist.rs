@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+
+use crate::PieceState;
+
+// random keys for Zobrist hashing, from a fixed seed so hashes are stable between runs
+struct Keys {
+    // [owner][piece][square]
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    // K, Q, k, q like Castling::rights
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        // splitmix64
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        let mut keys = Keys { pieces: [[[0; 64]; 6]; 2], black_to_move: 0, castling: [0; 4], en_passant_file: [0; 8] };
+        for owner in keys.pieces.iter_mut() {
+            for piece in owner.iter_mut() {
+                for key in piece.iter_mut() {
+                    *key = next();
+                }
+            }
+        }
+        keys.black_to_move = next();
+        for key in keys.castling.iter_mut().chain(keys.en_passant_file.iter_mut()) {
+            *key = next();
+        }
+
+        keys
+    })
+}
+
+pub fn piece(piece_state: PieceState, square: usize) -> u64 {
+    keys().pieces[piece_state.owner as usize][piece_state.piece as usize][square]
+}
+
+pub fn black_to_move() -> u64 {
+    keys().black_to_move
+}
+
+pub fn castling(rights: [bool; 4]) -> u64 {
+    rights.iter()
+        .zip(keys().castling.iter())
+        .filter(|(right, _)| **right)
+        .fold(0, |hash, (_, key)| hash ^ key)
+}
+
+pub fn en_passant_file(w: usize) -> u64 {
+    keys().en_passant_file[w]
+}
@@ -0,0 +1,117 @@
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use chess_core::external::{parse_info, ExternalEngine, ExternalError};
+use chess_core::{Limits, Position, Score};
+
+fn stub() -> ExternalEngine {
+    ExternalEngine::spawn(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/stub_engine.sh")).unwrap()
+}
+
+#[test]
+fn handshake_reads_the_name() {
+    assert_eq!(stub().name, "Stub");
+}
+
+#[test]
+fn go_returns_the_best_move_and_the_lines() {
+    let mut engine = stub();
+    let mut infos = Vec::new();
+
+    let best = engine.go(&Position::new(), &[], &Limits { depth: Some(2), ..Limits::default() }, &AtomicBool::new(false), |info| {
+        infos.push(info.clone());
+    });
+
+    assert_eq!(best.unwrap().map(|mv| mv.uci()), Some(String::from("e2e4")));
+    assert_eq!(infos.len(), 2);
+    assert_eq!(infos[0].score, Score::Cp(12));
+    assert_eq!(infos[0].pv.len(), 2);
+    assert_eq!(infos[1].multipv, 2);
+    assert_eq!(infos[1].score, Score::Mate(-3));
+    // the PV stops at the first move that is not legal
+    assert_eq!(infos[1].pv.len(), 1);
+}
+
+#[test]
+fn infinite_search_ends_on_stop() {
+    let mut engine = stub();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let flag = stop.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        flag.store(true, Ordering::Relaxed);
+    });
+
+    let best = engine.go(&Position::new(), &[], &Limits::default(), &stop, |_| {});
+    assert_eq!(best.unwrap().map(|mv| mv.uci()), Some(String::from("e2e4")));
+}
+
+#[test]
+fn silent_engine_times_out_after_the_move_time() {
+    let mut engine = stub();
+    let limits = Limits { movetime: Some(Duration::from_millis(100)), ..Limits::default() };
+
+    let best = engine.go(&Position::new(), &[], &limits, &AtomicBool::new(false), |_| {});
+    assert!(matches!(best, Err(ExternalError::Timeout("stop"))));
+}
+
+#[test]
+fn missing_executable_fails_to_spawn() {
+    assert!(matches!(ExternalEngine::spawn("/nonexistent/engine"), Err(ExternalError::Spawn(_, _))));
+}
+
+#[test]
+fn info_lines_without_a_score_are_skipped() {
+    let position = Position::new();
+    assert!(parse_info(&position, "info depth 5 currmove e2e4 currmovenumber 1").is_none());
+    assert!(parse_info(&position, "info string hello").is_none());
+    assert!(parse_info(&position, "bestmove e2e4").is_none());
+}
+
+#[test]
+fn own_uci_binary_finds_mate_in_one() {
+    let mut engine = ExternalEngine::spawn(env!("CARGO_BIN_EXE_uci")).unwrap();
+    let start = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+    let best = engine.go(&start, &[], &Limits { depth: Some(3), ..Limits::default() }, &AtomicBool::new(false), |_| {});
+    assert_eq!(best.unwrap().map(|mv| mv.uci()), Some(String::from("a1a8")));
+}
+
+#[test]
+fn own_uci_binary_takes_options_during_infinite_search() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "position startpos\ngo infinite\nsetoption name MultiPV value 2\nstop\nisready").unwrap();
+
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in stdout.lines().map_while(Result::ok) {
+            let _ = sender.send(line);
+        }
+    });
+
+    let mut answered = Vec::new();
+    while let Ok(line) = receiver.recv_timeout(Duration::from_secs(10)) {
+        if line.starts_with("bestmove") || line == "readyok" {
+            answered.push(line.split_whitespace().next().unwrap().to_string());
+        }
+        if line == "readyok" {
+            break;
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+
+    assert_eq!(answered, ["bestmove", "readyok"]);
+}
@@ -0,0 +1,60 @@
+// known perft numbers from https://www.chessprogramming.org/Perft_Results
+
+use chess_core::{divide, perft, Position};
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut position = Position::from_fen(fen).unwrap();
+
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(perft(&mut position, depth as u32 + 1), *nodes, "{} at depth {}", fen, depth + 1);
+    }
+
+    // make_move and unmake_move must leave the position exactly as it was
+    assert_eq!(position.to_fen(), fen);
+    assert_eq!(position.hash(), Position::from_fen(fen).unwrap().hash());
+}
+
+#[test]
+fn start_position() {
+    assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]);
+}
+
+#[test]
+fn position_4_mirrored() {
+    assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467, 422333]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let mut position = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let moves = divide(&mut position, 2);
+
+    assert_eq!(moves.len(), 48);
+    assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    assert!(moves.iter().any(|(mv, nodes)| mv.uci() == "e1g1" && *nodes == 43));
+}
@@ -0,0 +1,36 @@
+#!/bin/sh
+# a stand-in UCI engine for the tests: always suggests 1. e4
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Stub"
+            echo "id author tests"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        "go movetime"*)
+            # plays dead: no bestmove, not even after stop
+            ;;
+        "go infinite"*)
+            infinite=1
+            echo "info depth 1 score cp 12 nodes 10 time 1 pv e2e4 e7e5"
+            ;;
+        go*)
+            echo "info string thinking"
+            echo "info depth 1 score cp 12 nodes 10 time 1 pv e2e4 e7e5"
+            echo "info depth 2 multipv 2 score mate -3 nodes 20 time 2 pv d2d4 e9e5"
+            echo "bestmove e2e4"
+            ;;
+        stop)
+            if [ -n "$infinite" ]; then
+                echo "bestmove e2e4"
+            fi
+            infinite=
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done
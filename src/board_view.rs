@@ -0,0 +1,453 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer;
+use iced::advanced::text::{self, Text};
+use iced::advanced::widget::{tree, Tree, Widget};
+use iced::advanced::{Clipboard, Shell};
+use iced::{alignment, color, event, mouse, window, Color, Element, Event, Length, Pixels, Rectangle, Size};
+
+use chess_core::bitboard::LIGHT_SQUARES;
+use chess_core::{Board, ChessPiece, PieceState, Player, Point, CHESS_LEHGT};
+
+use crate::custom_theme::{ChessColor, ChessStyle};
+
+const TILE: f32 = 80.0;
+const PIECE_SIZE: f32 = 50.0;
+const LABEL_SIZE: f32 = 14.0;
+const LABEL_MARGIN: f32 = 4.0;
+
+// the whole board as one widget, so a piece can be dragged to its square as well as clicked there
+pub struct BoardView<'a, Message> {
+    board: Board,
+    // legal targets of the chosen piece
+    targets: &'a [Point],
+    checked: Option<Point>,
+    // from and to of the move that led here
+    last_move: Option<(Point, Point)>,
+    selected: Option<Point>,
+    // whose pieces can be picked up, None while nobody may move
+    movable: Option<Player>,
+    // Black at the bottom
+    flipped: bool,
+    on_select: Box<dyn Fn(Point, Option<PieceState>) -> Message + 'a>,
+    on_move: Box<dyn Fn(Point) -> Message + 'a>,
+    promotion: Option<Promotion<'a, Message>>,
+}
+
+// the choice of piece for a pawn that reached `square`
+struct Promotion<'a, Message> {
+    square: Point,
+    on_promote: Box<dyn Fn(ChessPiece) -> Message + 'a>,
+    on_cancel: Message,
+}
+
+// nearest the promotion square first
+const PROMOTION_CHOICES: [ChessPiece; 4] = [ChessPiece::Queen, ChessPiece::Knight, ChessPiece::Rook, ChessPiece::Bishop];
+
+#[derive(Debug, Default)]
+struct State {
+    // the square the piece under the cursor was picked up from
+    dragging: Option<Point>,
+}
+
+pub fn glyph(piece: ChessPiece) -> &'static str {
+    match piece {
+        ChessPiece::Bishop  => "♝",
+        ChessPiece::King    => "♚",
+        ChessPiece::Pawn    => "♙",
+        ChessPiece::Queen   => "♛",
+        ChessPiece::Rook    => "♜",
+        ChessPiece::Knight  => "♞",
+    }
+}
+
+fn piece_color(owner: Player) -> Color {
+    match owner {
+        Player::White => color!(0xffffff),
+        Player::Black => color!(0x000000),
+    }
+}
+
+impl<'a, Message> BoardView<'a, Message> {
+    // `on_select` fires for a press on any square that is not a target, `on_move` for a click
+    // or drop on a target of the chosen piece
+    pub fn new(
+        board: Board,
+        on_select: impl Fn(Point, Option<PieceState>) -> Message + 'a,
+        on_move: impl Fn(Point) -> Message + 'a,
+    ) -> Self {
+        BoardView {
+            board,
+            targets: &[],
+            checked: None,
+            last_move: None,
+            selected: None,
+            movable: None,
+            flipped: false,
+            on_select: Box::new(on_select),
+            on_move: Box::new(on_move),
+            promotion: None,
+        }
+    }
+
+    pub fn targets(mut self, targets: &'a [Point]) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    pub fn checked(mut self, checked: Option<Point>) -> Self {
+        self.checked = checked;
+        self
+    }
+
+    pub fn last_move(mut self, last_move: Option<(Point, Point)>) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    pub fn selected(mut self, selected: Option<Point>) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    pub fn movable(mut self, movable: Option<Player>) -> Self {
+        self.movable = movable;
+        self
+    }
+
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    // shows the choices over the board while `square` is Some; a click anywhere else cancels
+    pub fn promotion(
+        mut self,
+        square: Option<Point>,
+        on_promote: impl Fn(ChessPiece) -> Message + 'a,
+        on_cancel: Message,
+    ) -> Self {
+        self.promotion = square.map(|square| Promotion { square, on_promote: Box::new(on_promote), on_cancel });
+        self
+    }
+
+    // the choice tiles, running from the promotion square towards the middle of the board
+    fn promotion_tiles(&self, bounds: Rectangle) -> Vec<(ChessPiece, Rectangle)> {
+        let Some(promotion) = &self.promotion else {
+            return Vec::new();
+        };
+        let first = self.tile(bounds, &promotion.square);
+        let step = match self.orient(promotion.square.clone()).h {
+            0 => TILE,
+            _ => -TILE,
+        };
+
+        PROMOTION_CHOICES.iter().enumerate()
+            .map(|(i, piece)| (*piece, Rectangle { y: first.y + step * i as f32, ..first }))
+            .collect()
+    }
+
+    // turns a board point into a row and column on screen and back again
+    fn orient(&self, p: Point) -> Point {
+        match self.flipped {
+            true => Point { h: CHESS_LEHGT - 1 - p.h, w: CHESS_LEHGT - 1 - p.w },
+            false => p,
+        }
+    }
+
+    fn square_at(&self, bounds: Rectangle, position: iced::Point) -> Option<Point> {
+        if !bounds.contains(position) {
+            return None;
+        }
+        let w = ((position.x - bounds.x) / TILE) as usize;
+        let h = ((position.y - bounds.y) / TILE) as usize;
+        (h < CHESS_LEHGT && w < CHESS_LEHGT).then(|| self.orient(Point { h, w }))
+    }
+
+    fn tile(&self, bounds: Rectangle, p: &Point) -> Rectangle {
+        let screen = self.orient(p.clone());
+        Rectangle {
+            x: bounds.x + screen.w as f32 * TILE,
+            y: bounds.y + screen.h as f32 * TILE,
+            width: TILE,
+            height: TILE,
+        }
+    }
+
+    fn can_pick_up(&self, p: &Point) -> bool {
+        self.board.get(p).is_some_and(|piece| Some(piece.owner) == self.movable)
+    }
+}
+
+fn fill_piece<Renderer: text::Renderer>(renderer: &mut Renderer, piece: PieceState, center: iced::Point, viewport: &Rectangle) {
+    renderer.fill_text(
+        Text {
+            content: glyph(piece.piece).to_string(),
+            bounds: Size::new(TILE, TILE),
+            size: Pixels(PIECE_SIZE),
+            line_height: text::LineHeight::default(),
+            font: renderer.default_font(),
+            horizontal_alignment: alignment::Horizontal::Center,
+            vertical_alignment: alignment::Vertical::Center,
+            shaping: text::Shaping::Advanced,
+            wrapping: text::Wrapping::default(),
+        },
+        center,
+        piece_color(piece.owner),
+        *viewport,
+    );
+}
+
+fn fill_label<Renderer: text::Renderer>(
+    renderer: &mut Renderer,
+    label: String,
+    corner: iced::Point,
+    horizontal: alignment::Horizontal,
+    vertical: alignment::Vertical,
+    color: Color,
+    viewport: &Rectangle,
+) {
+    renderer.fill_text(
+        Text {
+            content: label,
+            bounds: Size::new(TILE, TILE),
+            size: Pixels(LABEL_SIZE),
+            line_height: text::LineHeight::default(),
+            font: renderer.default_font(),
+            horizontal_alignment: horizontal,
+            vertical_alignment: vertical,
+            shaping: text::Shaping::Basic,
+            wrapping: text::Wrapping::default(),
+        },
+        corner,
+        color,
+        *viewport,
+    );
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer> for BoardView<'_, Message>
+where
+    Message: Clone,
+    Renderer: text::Renderer,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fixed(TILE * CHESS_LEHGT as f32), Length::Fixed(TILE * CHESS_LEHGT as f32))
+    }
+
+    fn layout(&self, _tree: &mut Tree, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        let side = TILE * CHESS_LEHGT as f32;
+        layout::Node::new(limits.resolve(Length::Fixed(side), Length::Fixed(side), Size::new(side, side)))
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
+        let square = cursor.position().and_then(|position| self.square_at(bounds, position));
+
+        if let Some(promotion) = &self.promotion {
+            state.dragging = None;
+            if event != Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) || square.is_none() {
+                return event::Status::Ignored;
+            }
+
+            let chosen = self.promotion_tiles(bounds).into_iter()
+                .find(|(_, tile)| cursor.is_over(*tile))
+                .map(|(piece, _)| piece);
+            shell.publish(match chosen {
+                Some(piece) => (promotion.on_promote)(piece),
+                None => promotion.on_cancel.clone(),
+            });
+            return event::Status::Captured;
+        }
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(p) = square else {
+                    return event::Status::Ignored;
+                };
+
+                if self.targets.contains(&p) {
+                    shell.publish((self.on_move)(p));
+                } else {
+                    if self.can_pick_up(&p) {
+                        state.dragging = Some(p.clone());
+                    }
+                    shell.publish((self.on_select)(p.clone(), self.board.get(&p)));
+                }
+                event::Status::Captured
+            },
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if state.dragging.is_some() => {
+                shell.request_redraw(window::RedrawRequest::NextFrame);
+                event::Status::Captured
+            },
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let Some(from) = state.dragging.take() else {
+                    return event::Status::Ignored;
+                };
+
+                // letting go on the starting square leaves the piece chosen for a second click
+                if let Some(to) = square.filter(|to| *to != from && self.targets.contains(to)) {
+                    shell.publish((self.on_move)(to));
+                }
+                event::Status::Captured
+            },
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let state = tree.state.downcast_ref::<State>();
+        let bounds = layout.bounds();
+        let hovered = cursor.position().and_then(|position| self.square_at(bounds, position));
+
+        for square in 0..CHESS_LEHGT * CHESS_LEHGT {
+            let p = Point::from_square(square);
+            let tile = self.tile(bounds, &p);
+            let piece = self.board.get(&p);
+            let light = LIGHT_SQUARES & (1 << square) != 0;
+            let target = self.targets.contains(&p);
+            // while dragging only the squares the piece can land on react
+            let hover = hovered.as_ref() == Some(&p)
+                && (target || (state.dragging.is_none() && piece.is_some()));
+
+            let background = match self.checked.as_ref() == Some(&p) {
+                true if hover => ChessColor::check_bt_hovered(),
+                true => ChessColor::check_bt_active(),
+                false if light && hover && !target => ChessColor::bright_bt_hovered(),
+                false if light => ChessColor::bright_bt_active(),
+                false if hover && !target => ChessColor::dark_bt_hovered(),
+                false => ChessColor::dark_bt_active(),
+            };
+            renderer.fill_quad(renderer::Quad { bounds: tile, ..renderer::Quad::default() }, background);
+
+            let mut marks = Vec::new();
+            if self.last_move.as_ref().is_some_and(|(from, to)| *from == p || *to == p) {
+                marks.push(ChessStyle::last_move_mark(tile));
+            }
+            if self.selected.as_ref() == Some(&p) {
+                marks.push(ChessStyle::selected_mark(tile));
+            }
+            if target && hover {
+                marks.push(ChessStyle::target_hovered_mark(tile));
+            }
+            for (quad, background) in marks {
+                renderer.fill_quad(quad, background);
+            }
+
+            // files along the bottom edge, ranks along the left one, whichever way the board is turned
+            let label_color = match light {
+                true => ChessColor::dark_bt_active(),
+                false => ChessColor::bright_bt_active(),
+            };
+            let screen = self.orient(p.clone());
+            if screen.h == CHESS_LEHGT - 1 {
+                let file = char::from(b'a' + p.w as u8).to_string();
+                let corner = iced::Point::new(tile.x + tile.width - LABEL_MARGIN, tile.y + tile.height - LABEL_MARGIN);
+                fill_label(renderer, file, corner, alignment::Horizontal::Right, alignment::Vertical::Bottom, label_color, viewport);
+            }
+            if screen.w == 0 {
+                let rank = (CHESS_LEHGT - p.h).to_string();
+                let corner = iced::Point::new(tile.x + LABEL_MARGIN, tile.y + LABEL_MARGIN);
+                fill_label(renderer, rank, corner, alignment::Horizontal::Left, alignment::Vertical::Top, label_color, viewport);
+            }
+
+            if let Some(piece) = piece.filter(|_| state.dragging.as_ref() != Some(&p)) {
+                fill_piece(renderer, piece, tile.center(), viewport);
+            }
+
+            if target {
+                let (quad, background) = match piece {
+                    Some(_) => ChessStyle::target_ring_mark(tile),
+                    None => ChessStyle::target_dot_mark(tile),
+                };
+                renderer.fill_quad(quad, background);
+            }
+        }
+
+        // the choices go on a dimmed board; the promoting side is the one whose last rank it is
+        if let Some(promotion) = &self.promotion {
+            let owner = match promotion.square.h {
+                0 => Player::White,
+                _ => Player::Black,
+            };
+            renderer.with_layer(bounds, |renderer| {
+                let (quad, background) = ChessStyle::promotion_backdrop(bounds);
+                renderer.fill_quad(quad, background);
+                for (piece, tile) in self.promotion_tiles(bounds) {
+                    let (quad, background) = ChessStyle::promotion_choice_mark(tile, cursor.is_over(tile));
+                    renderer.fill_quad(quad, background);
+                    fill_piece(renderer, PieceState { owner, piece }, tile.center(), viewport);
+                }
+            });
+        }
+
+        // the dragged piece goes on top of everything, under the cursor
+        let dragged = state.dragging.as_ref().and_then(|from| self.board.get(from));
+        if let (Some(piece), Some(position)) = (dragged, cursor.position()) {
+            renderer.with_layer(*viewport, |renderer| {
+                fill_piece(renderer, piece, position, viewport);
+            });
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if self.promotion.is_some() {
+            return match self.promotion_tiles(layout.bounds()).iter().any(|(_, tile)| cursor.is_over(*tile)) {
+                true => mouse::Interaction::Pointer,
+                false => mouse::Interaction::default(),
+            };
+        }
+        if tree.state.downcast_ref::<State>().dragging.is_some() {
+            return mouse::Interaction::Grabbing;
+        }
+
+        match cursor.position().and_then(|position| self.square_at(layout.bounds(), position)) {
+            Some(p) if self.targets.contains(&p) => mouse::Interaction::Pointer,
+            Some(p) if self.can_pick_up(&p) => mouse::Interaction::Grab,
+            _ => mouse::Interaction::default(),
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<BoardView<'a, Message>> for Element<'a, Message, Theme, Renderer>
+where
+    Message: Clone + 'a,
+    Renderer: text::Renderer + 'a,
+{
+    fn from(board: BoardView<'a, Message>) -> Self {
+        Element::new(board)
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::{Duration, Instant};
+
+use chess_core::Player;
+
+// a period of the game: `moves` moves in `time`, or the rest of the game when `moves` is None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bonus {
+    // added after every move
+    Increment(Duration),
+    // the time used on a move is given back, up to the delay
+    Bronstein(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+    pub bonus: Option<Bonus>,
+}
+
+impl TimeControl {
+    pub const PRESETS: [&'static str; 5] = ["5+3", "15+10", "3+2d", "40/90+30+30", "90+30d"];
+
+    // minutes for the stages, then seconds for the bonus, all joined by '+':
+    // "5+3" is 5 minutes with a 3 second increment, "3+2d" a 2 second Bronstein delay,
+    // "40/90+30" 90 minutes for 40 moves and 30 more for the rest of the game
+    pub fn parse(text: &str) -> Option<TimeControl> {
+        let mut stages = Vec::new();
+        let mut bonus = None;
+
+        for part in text.split('+').map(str::trim) {
+            let sudden_death = stages.last().is_some_and(|stage: &Stage| stage.moves.is_none());
+
+            if sudden_death {
+                if bonus.is_some() {
+                    return None;
+                }
+                bonus = Some(match part.strip_suffix('d') {
+                    Some(seconds) => Bonus::Bronstein(Duration::from_secs(seconds.trim().parse().ok()?)),
+                    None => Bonus::Increment(Duration::from_secs(part.parse().ok()?)),
+                });
+            } else {
+                let (moves, minutes) = match part.split_once('/') {
+                    Some((moves, minutes)) => (Some(moves.trim().parse().ok().filter(|moves| *moves > 0)?), minutes.trim()),
+                    None => (None, part),
+                };
+                let minutes: f64 = minutes.parse().ok().filter(|minutes: &f64| *minutes > 0.0 && minutes.is_finite())?;
+                stages.push(Stage { moves, time: Duration::from_secs_f64(minutes * 60.0) });
+            }
+        }
+
+        // the last stage has to last until the end of the game
+        if stages.last()?.moves.is_some() {
+            return None;
+        }
+        Some(TimeControl { stages, bonus })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    // time left, as of the last pause or move
+    remaining: [Duration; 2],
+    // moves each player completed, to know when the next stage starts
+    moves: [u32; 2],
+    // the player whose move is being timed, also while paused
+    mover: Option<Player>,
+    // the running clock's start
+    since: Option<Instant>,
+    // time already spent on the current move before a pause, for the Bronstein delay
+    spent: Duration,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        let first = control.stages[0].time;
+        Clock { control, remaining: [first; 2], moves: [0; 2], mover: None, since: None, spent: Duration::ZERO }
+    }
+
+    // picks a saved game's clocks up again, both stopped
+    pub fn resume(control: TimeControl, remaining: [Duration; 2], moves: [u32; 2]) -> Clock {
+        Clock { remaining, moves, ..Clock::new(control) }
+    }
+
+    pub fn moves(&self) -> [u32; 2] {
+        self.moves
+    }
+
+    pub fn remaining(&self, player: Player, now: Instant) -> Duration {
+        let left = self.remaining[player as usize];
+        match self.since {
+            Some(since) if self.mover == Some(player) => left.saturating_sub(now - since),
+            _ => left,
+        }
+    }
+
+    pub fn running(&self) -> Option<Player> {
+        self.since.and(self.mover)
+    }
+
+    // the player whose time ran out
+    pub fn flagged(&self, now: Instant) -> Option<Player> {
+        self.running().filter(|player| self.remaining(*player, now).is_zero())
+    }
+
+    // runs `player`'s clock; a clock stopped without a move gets no bonus, as for a take-back
+    pub fn start(&mut self, player: Player, now: Instant) {
+        if self.running() == Some(player) {
+            return;
+        }
+        self.pause(now);
+        if self.mover != Some(player) {
+            self.mover = Some(player);
+            self.spent = Duration::ZERO;
+        }
+        self.since = Some(now);
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if let (Some(player), Some(since)) = (self.mover, self.since.take()) {
+            let used = now - since;
+            self.remaining[player as usize] = self.remaining[player as usize].saturating_sub(used);
+            self.spent += used;
+        }
+    }
+
+    // ends the timed move and stops the clocks until the next start
+    pub fn punch(&mut self, now: Instant) {
+        self.pause(now);
+        let Some(player) = self.mover.take() else {
+            return;
+        };
+
+        let side = player as usize;
+        match self.control.bonus {
+            Some(Bonus::Increment(increment)) => { self.remaining[side] += increment; },
+            Some(Bonus::Bronstein(delay)) => { self.remaining[side] += self.spent.min(delay); },
+            None => {},
+        }
+        self.spent = Duration::ZERO;
+
+        // the next stage's time comes on top of what is left once the stage's moves are made
+        self.moves[side] += 1;
+        let mut played = 0;
+        for (i, stage) in self.control.stages.iter().enumerate() {
+            let Some(moves) = stage.moves else {
+                break;
+            };
+            played += moves;
+            if self.moves[side] == played {
+                self.remaining[side] += self.control.stages[i + 1].time;
+            }
+        }
+    }
+}
+
+// "1:23:45", "4:05", and tenths in the last ten seconds: "9.3"
+pub fn format(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
@@ -1,10 +1,18 @@
-use iced::{Background, Border, Color, color, Shadow };
-use iced::widget::button;
+use iced::{Background, Border, Color, color, Rectangle, Shadow };
+use iced::advanced::renderer::Quad;
 
 pub struct ChessColor;
 impl ChessColor {
-    pub fn highlight_bt_active()    -> Color { color!(0xFF, 0xFF, 0x0) }
-    pub fn highlight_bt_hovered()   -> Color { color!(0xcc, 0xc0, 0xb4, 0.6) }
+    pub fn last_move()              -> Color { color!(0xcd, 0xd2, 0x6a, 0.6) }
+    pub fn selected()               -> Color { color!(0x64, 0x6f, 0x40, 0.6) }
+
+    pub fn target()                 -> Color { color!(0x14, 0x55, 0x1e, 0.5) }
+    pub fn target_hovered()         -> Color { color!(0x14, 0x55, 0x1e, 0.3) }
+
+    pub fn promotion_backdrop()     -> Color { color!(0x00, 0x00, 0x00, 0.5) }
+
+    pub fn check_bt_active()        -> Color { color!(0xe0, 0x4b, 0x4b) }
+    pub fn check_bt_hovered()       -> Color { color!(0xe0, 0x4b, 0x4b, 0.6) }
 
     pub fn dark_bt_active()         -> Color { color!(0xb6, 0x87, 0x6b) }
     pub fn dark_bt_hovered()        -> Color { color!(0xb6, 0x87, 0x6b, 0.6) }
@@ -15,48 +23,52 @@ impl ChessColor {
 
 pub struct ChessStyle;
 impl ChessStyle {
-    pub fn hightlighted_button_wrapper(status: iced::widget::button::Status) -> button::Style {
-        button::Style {
-            background: Some(Background::Color(
-                match status {
-                    button::Status::Active => ChessColor::highlight_bt_active(),
-                    button::Status::Hovered => ChessColor::highlight_bt_hovered(),
-                    _ => ChessColor::highlight_bt_active()
-                }
-            )),
-            text_color: Color::default(),
-            border: Border::default().rounded(0),
-            shadow: Shadow::default()
-        }
+    // marks drawn over a board square, each filling `tile` or a part of it
+    pub fn last_move_mark(tile: Rectangle) -> (Quad, Background) {
+        (Quad { bounds: tile, ..Quad::default() }, Background::Color(ChessColor::last_move()))
+    }
+
+    pub fn selected_mark(tile: Rectangle) -> (Quad, Background) {
+        (Quad { bounds: tile, ..Quad::default() }, Background::Color(ChessColor::selected()))
+    }
+
+    pub fn target_hovered_mark(tile: Rectangle) -> (Quad, Background) {
+        (Quad { bounds: tile, ..Quad::default() }, Background::Color(ChessColor::target_hovered()))
+    }
+
+    // a dot in the middle of an empty square the chosen piece can go to
+    pub fn target_dot_mark(tile: Rectangle) -> (Quad, Background) {
+        let size = tile.width * 0.3;
+        let bounds = Rectangle {
+            x: tile.center_x() - size / 2.0,
+            y: tile.center_y() - size / 2.0,
+            width: size,
+            height: size,
+        };
+        (
+            Quad { bounds, border: Border::default().rounded(size / 2.0), shadow: Shadow::default() },
+            Background::Color(ChessColor::target()),
+        )
     }
 
-    pub fn dark_button_wrapper(status: iced::widget::button::Status) -> button::Style {
-        button::Style {
-            background: Some(Background::Color(
-                match status {
-                    button::Status::Active   => ChessColor::dark_bt_active(),
-                    button::Status::Hovered  => ChessColor::dark_bt_hovered(),
-                    _ => ChessColor::dark_bt_active()
-                }
-            )),
-            text_color: Color::default(),
-            border: Border::default().rounded(0),
-            shadow: Shadow::default()
-        }
+    // a ring around a piece it can take
+    pub fn target_ring_mark(tile: Rectangle) -> (Quad, Background) {
+        let border = Border { color: ChessColor::target(), width: tile.width * 0.08, radius: (tile.width / 2.0).into() };
+        (Quad { bounds: tile, border, shadow: Shadow::default() }, Background::Color(Color::TRANSPARENT))
     }
 
-    pub fn bright_button_wrapper(status: iced::widget::button::Status) -> button::Style {
-        button::Style {
-            background: Some(Background::Color(
-                match status {
-                    button::Status::Active => ChessColor::bright_bt_active(),
-                    button::Status::Hovered => ChessColor::bright_bt_hovered(),
-                    _ => ChessColor::bright_bt_active()
-                }
-            )),
-            text_color: Color::default(),
-            border: Border::default().rounded(0),
-            shadow: Shadow::default()
-        }
+    pub fn promotion_backdrop(bounds: Rectangle) -> (Quad, Background) {
+        (Quad { bounds, ..Quad::default() }, Background::Color(ChessColor::promotion_backdrop()))
     }
-}
\ No newline at end of file
+
+    pub fn promotion_choice_mark(tile: Rectangle, hovered: bool) -> (Quad, Background) {
+        let color = match hovered {
+            true => ChessColor::dark_bt_active(),
+            false => ChessColor::bright_bt_active(),
+        };
+        (
+            Quad { bounds: tile, border: Border::default().rounded(tile.width / 2.0), shadow: Shadow::default() },
+            Background::Color(color),
+        )
+    }
+}
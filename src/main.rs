@@ -1,542 +1,896 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 use iced::{
-    Alignment, Task as Command, Element, Length, color
+    keyboard, Alignment, Task as Command, Element, Length, color
 };
 // use iced::theme::{self, Theme};
+use iced::futures::channel::{mpsc, oneshot};
 use iced::widget::{
-    button, column, container, row, text, Column, Row
+    button, checkbox, column, container, pick_list, row, scrollable, slider, text, text_input, Column, Row
+};
+
+use chess_core::{
+    external::ExternalEngine, pgn, ChessPiece, DrawReason, Engine, GameResult, Limits, Move, PieceState, Player, Point, Position,
+    PositionKey, Score, SearchInfo, WinReason, CHESS_LEHGT, MAX_SKILL_LEVEL,
+    attack_map, find_king, has_legal_move, has_mating_material, is_checked, is_insufficient_material, legal_move_able,
 };
 
 mod modal;
 mod custom_theme;
+mod settings;
+mod clock;
+mod board_view;
+mod saved_game;
+
+use board_view::BoardView;
+use clock::{Clock, TimeControl};
+use saved_game::SavedGame;
+use settings::{LimitKind, Opponent, Settings};
 
 
 pub fn main() -> iced::Result {
     iced::application(Chess::title, Chess::update, Chess::view)
+    .subscription(Chess::subscription)
     .run_with(Chess::new)
 }
 
 
-const CHESS_LEHGT: usize = 8;
-
-#[derive(Clone, PartialEq, Copy, Debug)]
-enum ChessPiece {
-    King,
-    Queen,
-    Rook,
-    Bishop,
-    Knight,
-    Pawn,
-}
-
-#[derive(Debug, Clone, PartialEq, Copy)]
-enum Player {
-    White,
-    Black,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct PieceState {
-    owner: Player,
-    piece: ChessPiece,
-}
-
-#[derive(Clone, Copy)]
-struct TileState {
-    piece_state: Option<PieceState>,
-    high_light: bool,
+struct Chess {
+    position: Position,
+    choose: Option<(Point, PieceState)>,
+    // legal targets of the chosen piece, view state only
+    high_light: Vec<Point>,
+    // a pawn move waiting for the piece it promotes to; nothing is played until then
+    pending_promotion: Option<Move>,
+    // the result at the shown ply
+    game_result: Option<GameResult>,
+    // how the game ended, kept while browsing back through it
+    final_result: Option<GameResult>,
+    // the game-over box was closed, the finished game stays on the board
+    result_dismissed: bool,
+    repetition: HashMap<PositionKey, u32>,
+    start_position: Position,
+    moves: Vec<MoveRecord>,
+    ply: usize,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    fen_input: String,
+    fen_error: Option<String>,
+    pgn_path: String,
+    pgn_message: Option<String>,
+    pgn_games: Vec<pgn::PgnGame>,
+    settings: Settings,
+    // the mode, players and clock of the game on the board; the settings are only the defaults for a new game
+    opponent: Opponent,
+    names: [String; 2],
+    time_control: String,
+    // the choices being edited in the new-game dialog
+    new_game: Option<Settings>,
+    limit_input: String,
+    engine: Arc<Mutex<Engine>>,
+    thinking: bool,
+    // the UCI engine from the settings, started when first needed
+    external: Option<Arc<Mutex<ExternalEngine>>>,
+    // set after the external engine failed, the built-in one takes over until the next game
+    external_failed: bool,
+    // the external engine's handshake is under way, the engines wait for it
+    external_starting: bool,
+    // the external engine has not heard "ucinewgame" since the game on the board started
+    external_new_game: bool,
+    engine_message: Option<String>,
+    analysis: bool,
+    analysis_session: u64,
+    analysis_stop: Arc<AtomicBool>,
+    // hash of the position under analysis
+    analysed: Option<u64>,
+    // the best lines so far, first line first
+    analysis_lines: Vec<SearchInfo>,
+    analysis_multi_pv: usize,
+    // (line, move) of the clicked PV move and the position after it
+    preview: Option<(usize, usize, Position)>,
+    // None for a game without time control
+    clock: Option<Clock>,
+    // turned by hand, on top of the automatic orientation
+    flipped: bool,
+    // the unfinished game from the last run, until the player decides about it
+    resume_offer: Option<SavedGame>,
+    // when the game was last written, to save the running clock now and then
+    saved_at: Instant,
 }
 
-
+// everything undo and redo have to bring back
 #[derive(Debug, Clone)]
-struct Point {
-    h: usize,
-    w: usize,
-}
-
-type Board = Vec<Vec<TileState>>;
-
-struct Chess {
-    board: Board,
-    turn: Player,
-    choose: Option<(Point, PieceState)>,
-    en_passant: Option<Point>,
-    castling: Castling,
-    promotion_popup: bool,
-    game_over: bool,
+struct Snapshot {
+    position: Position,
+    game_result: Option<GameResult>,
+    final_result: Option<GameResult>,
+    repetition: HashMap<PositionKey, u32>,
+    moves: Vec<MoveRecord>,
+    ply: usize,
+    // stopped, so a take-back also takes back the bonus and stage time of the move
+    clock: Option<Clock>,
 }
 
-struct Castling {
-    w_r_rook: bool,
-    w_l_rook: bool,
-    w_king: bool,
-    b_r_rook: bool,
-    b_l_rook: bool,
-    b_king: bool,
+#[derive(Debug, Clone)]
+struct MoveRecord {
+    mv: Move,
+    san: String,
+    position: Position,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     MoveAble(Point, Option<PieceState>),
     Move(Point),
-    Promotion(PieceState),
+    Promotion(ChessPiece),
+    CancelPromotion,
+    ClaimDraw,
+    CopyFen,
+    FenInput(String),
+    LoadFen,
+    PgnPathInput(String),
+    ExportPgn,
+    ImportPgn,
+    LoadGame(usize),
+    CloseGamePicker,
+    GoTo(usize),
+    Previous,
+    Next,
+    Undo,
+    Redo,
     Reset,
+    DismissResult,
+    OpenNewGame,
+    CloseNewGame,
+    OpponentSelected(Opponent),
+    LimitSelected(LimitKind),
+    LimitInput(String),
+    SkillChanged(u32),
+    StartGame,
+    EnginePathInput(String),
+    TimeControlInput(String),
+    Tick,
+    FlipBoard,
+    AutoOrientation(bool),
+    NameInput(Player, String),
+    ResumeGame,
+    DiscardSavedGame,
+    // the engine path it was started from, to drop an engine the settings no longer name
+    ExternalEngineStarted(String, Result<Arc<Mutex<ExternalEngine>>, String>),
+    // the hash of the position the search started from, to drop stale answers
+    EngineMove(u64, Result<Option<Move>, String>),
+    ToggleAnalysis,
+    // the analysis session the line belongs to
+    AnalysisInfo(u64, SearchInfo),
+    AnalysisLinesSelected(usize),
+    // shows the position after the given move of the given line
+    Preview(usize, usize),
+    ClosePreview,
 }
 
-fn init_board() -> Board {
-    let mut board: Board = vec![vec![TileState {piece_state: None, high_light: false}; CHESS_LEHGT]; CHESS_LEHGT];
-
-    // ♜♞♝♛♚♝♞♜
-    let chess_seq: [ChessPiece; CHESS_LEHGT] = [
-                        ChessPiece::Rook,
-                        ChessPiece::Knight,
-                        ChessPiece::Bishop,
-                        ChessPiece::Queen,
-                        ChessPiece::King,
-                        ChessPiece::Bishop,
-                        ChessPiece::Knight,
-                        ChessPiece::Rook,
-                        ];
-
-    for i in 0..CHESS_LEHGT {
-        board[0][i] = TileState {piece_state: Some( PieceState {owner: Player::Black , piece: chess_seq[i].clone() }), high_light: false};
-        board[1][i] = TileState {piece_state: Some( PieceState {owner: Player::Black , piece: ChessPiece::Pawn }), high_light: false};
-
-        
-        board[6][i] = TileState {piece_state: Some( PieceState {owner: Player::White , piece: ChessPiece::Pawn }), high_light: false};
-        board[7][i] = TileState {piece_state: Some( PieceState {owner: Player::White , piece: chess_seq[i].clone() }), high_light: false};
+const ANALYSIS_LINES: [usize; 5] = [1, 2, 3, 4, 5];
+// longer lines do not fit beside the board
+const ANALYSIS_PV_MOVES: usize = 10;
+// how often a running clock is written to the saved game
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+// engine scores come from the side to move, show them from White's side: "+0.35", "#-3"
+fn score_label(score: Score, turn: Player) -> String {
+    let sign = if turn == Player::White { 1 } else { -1 };
+    match score {
+        Score::Cp(cp) => format!("{:+.2}", (sign * cp) as f64 / 100.0),
+        Score::Mate(moves) => format!("#{}", sign * moves),
     }
+}
 
-    board
+// White's share of the bar, from 0 (lost) to 1000 (won)
+fn white_share(score: Score, turn: Player) -> u16 {
+    let sign = if turn == Player::White { 1.0 } else { -1.0 };
+    let share = match score {
+        Score::Cp(cp) => 1.0 / (1.0 + (-sign * cp as f64 / 400.0).exp()),
+        Score::Mate(moves) if sign * (moves as f64) > 0.0 => 1.0,
+        Score::Mate(_) => 0.0,
+    };
+    (share * 1000.0).round().clamp(1.0, 999.0) as u16
 }
 
-fn view_tile(tile: &TileState, h: usize, w: usize) -> Element<Message>  {
-    let b;
-    let piece_color;
+// White's part is at the bottom when White is
+fn view_eval_bar(score: Option<Score>, turn: Player, bottom: Player) -> Element<'static, Message> {
+    let white = score.map_or(500, |score| white_share(score, turn));
+    let part = |portion: u16, background: iced::Color| {
+        container(text(""))
+            .width(Length::Fill)
+            .height(Length::FillPortion(portion))
+            .style(move |_| container::Style::default().background(background))
+    };
 
-    if let Some(x) = &tile.piece_state {
-        match x.owner {
-            Player::White => { piece_color = color!(0xffffff); },
-            Player::Black => { piece_color = color!(0x000000); },
-        };
+    let black = part(1000 - white, color!(0x303030));
+    let white = part(white, color!(0xf0f0f0));
+    match bottom {
+        Player::White => column![black, white],
+        Player::Black => column![white, black],
+    }
+    .width(Length::Fixed(20.0))
+    .height(Length::Fixed(640.0))
+    .into()
+}
 
-        let piece = match x.piece {
-            ChessPiece::Bishop  => { text("♝") },
-            ChessPiece::King    => { text("♚") },
-            ChessPiece::Pawn    => { text("♙") },
-            ChessPiece::Queen   => { text("♛") },
-            ChessPiece::Rook    => { text("♜") },
-            ChessPiece::Knight  => { text("♞") },
-        }
-        .size(50.0)
-        .color(piece_color)
-        .shaping(text::Shaping::Advanced)
-        .center();
-        
-        if tile.high_light == true {
-            b = button(piece).on_press(Message::Move(Point{ h, w }));
-        } else {
-            b = button(piece).on_press(Message::MoveAble(Point{ h, w }, Some(*x)));
-        }
-        
-    } else {
+impl Chess {
 
-        if tile.high_light == true {
-            b = button(" ").on_press(Message::Move(Point{ h, w }));
-        } else {
-            b = button(" ");
-        }
+    fn new() -> (Self, Command<Message>) {
+        let mut chess = Self {
+            position: Position::new(),
+            choose: None,
+            high_light: Vec::new(),
+            pending_promotion: None,
+            game_result: None,
+            final_result: None,
+            result_dismissed: false,
+            repetition: HashMap::new(),
+            start_position: Position::new(),
+            moves: Vec::new(),
+            ply: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            fen_input: String::new(),
+            fen_error: None,
+            pgn_path: String::from("game.pgn"),
+            pgn_message: None,
+            pgn_games: Vec::new(),
+            settings: Settings::load(),
+            opponent: Opponent::Human,
+            names: [String::from("White"), String::from("Black")],
+            time_control: String::new(),
+            new_game: None,
+            limit_input: String::new(),
+            engine: Arc::new(Mutex::new(Engine::new())),
+            thinking: false,
+            external: None,
+            external_failed: false,
+            external_starting: false,
+            external_new_game: true,
+            engine_message: None,
+            analysis: false,
+            analysis_session: 0,
+            analysis_stop: Arc::new(AtomicBool::new(false)),
+            analysed: None,
+            analysis_lines: Vec::new(),
+            analysis_multi_pv: 3,
+            preview: None,
+            clock: None,
+            flipped: false,
+            resume_offer: SavedGame::load().filter(|game| !game.moves.is_empty()),
+            saved_at: Instant::now(),
+        };
+        chess.use_settings();
+        chess.set_position(Position::new());
+        let command = chess.refresh();
+
+        (
+            chess,
+            command
+        )
     }
-    
-    
-    let butten_style = match tile.high_light {
-        true => { custom_theme::ChessStyle::hightlighted_button_wrapper },
-        false => {
-            match (h, w) {
-                (h, w) if (h % 2) ^ (w % 2) == 0 => {custom_theme::ChessStyle::bright_button_wrapper},
-                (_,_) => {custom_theme::ChessStyle::dark_button_wrapper},
-            }
-        }
-    };
 
-    b.height(Length::Fixed(80.0)).width(Length::Fixed(80.0))
-        .style(
-            move |_,state| butten_style(state))
-        .into()
-}
+    // the next game is played the way the settings say
+    fn use_settings(&mut self) {
+        self.opponent = self.settings.opponent;
+        self.names = self.settings.names.clone();
+        self.time_control = self.settings.time_control.clone();
+    }
 
-fn move_able_rook(board: &Board, p: &Point) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
+    // starts a fresh game from the given position
+    fn set_position(&mut self, position: Position) {
+        self.start_position = position.clone();
+        self.position = position;
+        self.moves = Vec::new();
+        self.ply = 0;
+        self.undo_stack = Vec::new();
+        self.redo_stack = Vec::new();
+        self.choose = None;
+        self.high_light = Vec::new();
+        self.pending_promotion = None;
+        self.game_result = None;
+        self.final_result = None;
+        self.result_dismissed = false;
+        self.repetition = HashMap::new();
+        self.clock = TimeControl::parse(&self.time_control).map(Clock::new);
+        self.external_new_game = true;
+        self.finish_move();
+    }
 
-    let w = p.w;
-    let h = p.h;
+    // a move made on the board, which can be undone
+    fn play_move(&mut self, mv: Move) {
+        self.undo_stack.push(self.snapshot());
+        self.redo_stack = Vec::new();
+        self.apply_move(mv);
+    }
 
-    let piece = board[h][w].piece_state.clone().unwrap();
+    // plays a move decided outside the board clicks, e.g. from a PGN file
+    fn apply_move(&mut self, mv: Move) {
+        let before = self.position.clone();
+        self.position.make_move(&mv);
+        self.record_move(&before, &mv);
+        self.finish_move();
+    }
 
-    let mut dw = w as i32 - 1;
-    while dw >= 0{
-        if let Some(x) = board[h][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h, w: dw as usize} );
-            }
-            break;
+    fn load_game(&mut self, index: usize) {
+        let game = self.pgn_games[index].clone();
+        self.pgn_games = Vec::new();
+
+        match pgn::replay(&game, index) {
+            Ok((start, moves)) => {
+                // a game is loaded to be replayed, the computer would add moves of its own
+                self.opponent = Opponent::Human;
+                self.names = [
+                    game.tag("White").unwrap_or("White").to_string(),
+                    game.tag("Black").unwrap_or("Black").to_string(),
+                ];
+                self.set_position(start);
+                for mv in moves {
+                    if self.game_result.is_some() {
+                        break;
+                    }
+                    self.apply_move(mv);
+                }
+                self.pgn_message = Some(format!("loaded {}", game.title(index)));
+            },
+            Err(e) => {
+                self.pgn_message = Some(e.to_string());
+            },
         }
-        high_light.push(Point {h: h as usize, w: dw as usize} );
-        dw -= 1;
     }
 
-    let mut dw = w as i32 + 1;
-    while dw < CHESS_LEHGT as i32 {
-        if let Some(x) = board[h][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h, w: dw as usize} );
-            }
-            break;
+    fn record_move(&mut self, before: &Position, mv: &Move) {
+        let san = pgn::san(before, mv);
+
+        // a new move from an earlier position drops the old continuation
+        self.moves.truncate(self.ply);
+        self.moves.push(MoveRecord { mv: mv.clone(), san, position: self.position.clone() });
+        self.ply = self.moves.len();
+
+        if let Some(clock) = &mut self.clock {
+            clock.punch(Instant::now());
         }
-        high_light.push(Point {h, w: dw as usize} );
-        dw += 1;
     }
 
-    let mut dh = h as i32 + 1;
-    while dh < CHESS_LEHGT as i32 {
-        if let Some(x) = board[dh as usize][w].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w });
-            }
-            break;
+    fn snapshot(&self) -> Snapshot {
+        let mut clock = self.clock.clone();
+        if let Some(clock) = &mut clock {
+            clock.pause(Instant::now());
         }
-        high_light.push(Point {h : dh as usize, w });
-        dh += 1;   
-    }
 
-    let mut dh = h as i32 - 1;
-    while dh >= 0{
-        if let Some(x) = board[dh as usize][w].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w });
-            }
-            break;
+        Snapshot {
+            position: self.position.clone(),
+            game_result: self.game_result,
+            final_result: self.final_result,
+            repetition: self.repetition.clone(),
+            moves: self.moves.clone(),
+            ply: self.ply,
+            clock,
         }
-        high_light.push(Point {h : dh as usize, w });
-        dh -= 1;   
     }
 
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.position = snapshot.position;
+        self.pending_promotion = None;
+        self.game_result = snapshot.game_result;
+        self.final_result = snapshot.final_result;
+        self.result_dismissed = false;
+        self.repetition = snapshot.repetition;
+        self.moves = snapshot.moves;
+        self.ply = snapshot.ply;
+        self.clock = snapshot.clock;
+        self.choose = None;
+        self.high_light = Vec::new();
+    }
 
-    high_light
-}
-
-fn move_able_bishop(board: &Board, p: &Point) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
-
-    let w = p.w;
-    let h = p.h;
+    // shows the position after `ply` moves, keeping the later moves
+    fn go_to(&mut self, ply: usize) {
+        if self.pending_promotion.is_some() || ply > self.moves.len() {
+            return;
+        }
 
-    let piece = board[h][w].piece_state.clone().unwrap();
+        self.ply = ply;
+        self.position = match ply {
+            0 => self.start_position.clone(),
+            _ => self.moves[ply - 1].position.clone(),
+        };
+        self.choose = None;
+        self.high_light = Vec::new();
+
+        self.repetition = HashMap::new();
+        let positions: Vec<PositionKey> = std::iter::once(&self.start_position)
+            .chain(self.moves[..ply].iter().map(|record| &record.position))
+            .map(|position| position.key())
+            .collect();
+        for key in positions {
+            *self.repetition.entry(key).or_insert(0) += 1;
+        }
 
-    let mut dh = h as i32 - 1;
-    let mut dw = w as i32 - 1;
-    while dh >= 0 && dw >= 0 {
-        if let Some(x) = board[dh as usize][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w: dw as usize });
-            }
-            break;
+        // a claimed draw or a flag fall cannot be worked out again from the position
+        if ply == self.moves.len() {
+            self.game_result = self.final_result;
+        } else {
+            self.game_result = None;
+            self.update_game_result();
         }
-        high_light.push(Point {h : dh as usize, w: dw as usize });
+    }
 
-        dh -= 1;
-        dw -= 1;
+    // the position the game has reached, whichever ply is shown
+    fn game_position(&self) -> &Position {
+        self.moves.last().map_or(&self.start_position, |record| &record.position)
     }
 
-    let mut dh = h as i32 + 1;
-    let mut dw = w as i32 - 1;
-    while dh < CHESS_LEHGT as i32 && dw >= 0 {
-        if let Some(x) = board[dh as usize][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w: dw as usize });
-            }
-            break;
-        }
-        high_light.push(Point {h : dh as usize, w: dw as usize });
+    fn record_position(&mut self) {
+        *self.repetition.entry(self.position.key()).or_insert(0) += 1;
+    }
 
-        dh += 1;
-        dw -= 1;
+    fn repetition_count(&self) -> u32 {
+        self.repetition.get(&self.position.key()).copied().unwrap_or(0)
     }
 
-    let mut dh = h as i32 - 1;
-    let mut dw = w as i32 + 1;
-    while dh >= 0 && dw < CHESS_LEHGT as i32 {
-        if let Some(x) = board[dh as usize][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w: dw as usize });
-            }
-            break;
+    fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.game_result.is_some() || self.pending_promotion.is_some() || self.ply != self.moves.len() {
+            None
+        } else if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.position.halfmove_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
         }
-        high_light.push(Point {h : dh as usize, w: dw as usize });
+    }
 
-        dh -= 1;
-        dw += 1;
+    fn finish_move(&mut self) {
+        self.record_position();
+        self.update_game_result();
+        self.final_result = self.game_result;
+        self.result_dismissed = false;
     }
 
-    let mut dh = h as i32 + 1;
-    let mut dw = w as i32 + 1;
-    while dh < CHESS_LEHGT as i32 && dw < CHESS_LEHGT as i32 {
-        if let Some(x) = board[dh as usize][dw as usize].piece_state {
-            if x.owner != piece.owner {
-                high_light.push(Point {h : dh as usize, w: dw as usize });
-            }
-            break;
+    fn update_game_result(&mut self) {
+        if !has_legal_move(&self.position.board, self.position.turn, &self.position.en_passant, &self.position.castling) {
+            self.game_result = match is_checked(&self.position.board, self.position.turn) {
+                true => Some(GameResult::Win(self.position.turn.enemy(), WinReason::Checkmate)),
+                false => Some(GameResult::Draw(DrawReason::Stalemate)),
+            };
+        } else if is_insufficient_material(&self.position.board) {
+            self.game_result = Some(GameResult::Draw(DrawReason::InsufficientMaterial));
+        } else if self.repetition_count() >= 5 {
+            self.game_result = Some(GameResult::Draw(DrawReason::FivefoldRepetition));
+        } else if self.position.halfmove_clock >= 150 {
+            self.game_result = Some(GameResult::Draw(DrawReason::SeventyFiveMoveRule));
         }
-        high_light.push(Point {h : dh as usize, w: dw as usize });
-
-        dh += 1;
-        dw += 1;
     }
 
+    // the side shown at the bottom of the board
+    fn bottom_player(&self) -> Player {
+        let player = match (self.settings.auto_orientation, self.opponent) {
+            (false, _) => Player::White,
+            (true, Opponent::Human) => self.position.turn,
+            (true, Opponent::Computer(computer)) => computer.enemy(),
+        };
+        match self.flipped {
+            true => player.enemy(),
+            false => player,
+        }
+    }
 
-    high_light
-}
-
-fn move_able_king(board: &Board, p: &Point) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
-
-    let w = p.w;
-    let h = p.h;
+    fn computer_to_move(&self) -> bool {
+        self.opponent == Opponent::Computer(self.position.turn)
+    }
 
-    let piece = board[h][w].piece_state.clone().unwrap();
+    // a move can be made on the board; a finished game can be looked through but not played on,
+    // and with clocks only the latest position, as the running clock belongs to its side
+    fn can_play(&self) -> bool {
+        self.final_result.is_none() && !self.computer_to_move()
+            && (self.clock.is_none() || self.ply == self.moves.len())
+    }
 
-    for dh in -1..=1 {
-        for dw in -1..=1 {
-            if ((h as i32 + dh >= 0) && (h as i32 + dh < CHESS_LEHGT as i32)) && ((w as i32 + dw >= 0) && (w as i32 + dw < CHESS_LEHGT as i32)) {
-                if dh == 0 && dw == 0 { continue; }
+    // None once the engine to use is ready; otherwise the external engine is started on first use,
+    // on a thread as its handshake can take seconds, and the caller waits for ExternalEngineStarted
+    fn start_external(&mut self) -> Option<Command<Message>> {
+        if self.settings.engine_path.is_empty() || self.external_failed || self.external.is_some() {
+            return None;
+        }
+        if self.external_starting {
+            return Some(Command::none());
+        }
+        self.external_starting = true;
+
+        let path = self.settings.engine_path.clone();
+        Some(Command::perform(
+            async move {
+                let (sender, receiver) = oneshot::channel();
+                let spawn_path = path.clone();
+                std::thread::spawn(move || {
+                    let engine = ExternalEngine::spawn(&spawn_path)
+                        .map(|engine| Arc::new(Mutex::new(engine)))
+                        .map_err(|e| e.to_string());
+                    let _ = sender.send(engine);
+                });
+                let engine = receiver.await.unwrap_or_else(|_| Err(String::from("the engine did not start")));
+                (path, engine)
+            },
+            |(path, engine)| Message::ExternalEngineStarted(path, engine),
+        ))
+    }
 
-                if let Some(x) = board[(h as i32 + dh) as usize][(w as i32 + dw) as usize].piece_state {
-                    if x.owner != piece.owner {
-                        high_light.push(Point { h: (h as i32 + dh) as usize, w : (w as i32 + dw) as usize});
-                    }
-                } else {
-                    high_light.push(Point { h: (h as i32 + dh) as usize, w : (w as i32 + dw) as usize});
-                }                
-            }
-        }    
+    fn refresh(&mut self) -> Command<Message> {
+        self.sync_clock();
+        self.autosave();
+        let think = self.think();
+        Command::batch([think, self.analyse()])
     }
 
-    high_light
-}
+    // keeps the file in step with the game; a finished game has nothing to resume
+    fn autosave(&mut self) {
+        if self.resume_offer.is_some() {
+            return;
+        }
+        let now = Instant::now();
+        self.saved_at = now;
+        if self.final_result.is_some() {
+            SavedGame::remove();
+            return;
+        }
 
-fn move_able_knight(board: &Board, p: &Point) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
-
-    let w = p.w as i32;
-    let h = p.h as i32;
-
-    let piece = board[h as usize][w as usize].piece_state.clone().unwrap();
-    
-    let list = [(2, 1), 
-                                 (2, -1),
-                                 (-2, 1),
-                                 (-2, -1),
-                                 (1, 2),
-                                 (1, -2),
-                                 (-1, 2),
-                                 (-1, -2),
-                                 ];
-
-    for (dh, dw) in list {
-        if h + dh >= 0 && h + dh < CHESS_LEHGT as i32 && w + dw >= 0 && w + dw < CHESS_LEHGT as i32 {
-            
-            if let Some(x) = board[(h + dh) as usize][(w + dw) as usize].piece_state {
-                if x.owner != piece.owner {
-                    high_light.push(Point { h: (h + dh) as usize, w : (w + dw) as usize});
-                }
-            } else {
-                high_light.push(Point { h: (h + dh) as usize, w : (w + dw) as usize});
-            }
+        let game = SavedGame {
+            start: self.start_position.clone(),
+            moves: self.moves.iter().map(|record| record.mv.clone()).collect(),
+            opponent: self.opponent,
+            names: self.names.clone(),
+            time_control: self.time_control.clone(),
+            clocks: self.clock.as_ref().map(|clock| {
+                ([clock.remaining(Player::White, now), clock.remaining(Player::Black, now)], clock.moves())
+            }),
+        };
+        if let Err(e) = game.save() {
+            eprintln!("could not save the game: {}", e);
         }
     }
 
+    // the side to move in an unfinished game uses time, also while looking back through the moves;
+    // the promotion choice is free
+    fn sync_clock(&mut self) {
+        let live = self.final_result.is_none() && self.pending_promotion.is_none() && self.resume_offer.is_none();
+        let turn = self.game_position().turn;
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
 
-    high_light
-}
-
-fn move_able_pawn(board: &Board, p: &Point, en_passant: &Option<Point>) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
-
-    let h = p.h;
-    let w = p.w;
+        let now = Instant::now();
+        match live {
+            true => clock.start(turn, now),
+            false => clock.pause(now),
+        }
+    }
 
-    let piece = board[h][w].piece_state.clone().unwrap();
-    match piece.owner {
-        Player::Black => {
-            if h == 1 {
-                let mut dh = 1;
-                while dh <= 2 {
-                    if board[h + dh][w].piece_state == None {
-                        high_light.push(Point { h: h + dh, w });
-                    } else {
-                        break;
-                    }
-                    dh += 1;
-                }
-            } else {
-                if board[h + 1][w].piece_state == None {
-                    high_light.push(Point { h: h + 1, w });
-                }
+    // hands the game's position to the engine on a worker thread when it is the computer's turn,
+    // whichever ply is shown
+    fn think(&mut self) -> Command<Message> {
+        let position = self.game_position().clone();
+        if self.thinking || self.opponent != Opponent::Computer(position.turn) || self.final_result.is_some()
+            || self.pending_promotion.is_some() || self.resume_offer.is_some() {
+            return Command::none();
+        }
+        if let Some(starting) = self.start_external() {
+            return starting;
+        }
+        self.thinking = true;
+
+        let start = self.start_position.clone();
+        let moves: Vec<Move> = self.moves.iter().map(|record| record.mv.clone()).collect();
+        let history: Vec<u64> = std::iter::once(&self.start_position)
+            .chain(self.moves.iter().map(|record| &record.position))
+            .take(self.moves.len())
+            .map(|position| position.hash())
+            .collect();
+        let engine = self.engine.clone();
+        let external = self.external.clone();
+        let new_game = external.is_some() && std::mem::take(&mut self.external_new_game);
+        let hash = position.hash();
+        let mut limits = self.settings.limits();
+        // never more than a twentieth of the clock
+        if let Some(clock) = &self.clock {
+            let budget = clock.remaining(position.turn, Instant::now()) / 20;
+            limits.movetime = Some(limits.movetime.map_or(budget, |movetime| movetime.min(budget)));
+        }
+        let skill_level = self.settings.skill_level;
+
+        Command::perform(
+            async move {
+                let (sender, receiver) = oneshot::channel();
+                std::thread::spawn(move || {
+                    let mv = match external {
+                        Some(external) => {
+                            let mut external = external.lock().unwrap();
+                            let ready = match new_game {
+                                true => external.new_game(),
+                                false => Ok(()),
+                            };
+                            // the analysis may have left more lines set
+                            ready
+                                .and_then(|_| external.set_option("MultiPV", "1"))
+                                .and_then(|_| external.go(&start, &moves, &limits, &AtomicBool::new(false), |_| {}))
+                                .map_err(|e| e.to_string())
+                        },
+                        None => {
+                            let mut engine = engine.lock().unwrap();
+                            engine.set_skill_level(skill_level);
+                            engine.set_multi_pv(1);
+                            Ok(engine.search(&position, &history, &limits, &Arc::new(AtomicBool::new(false)), |_| {}))
+                        },
+                    };
+                    let _ = sender.send(mv);
+                });
+                receiver.await.unwrap_or(Ok(None))
+            },
+            move |mv| Message::EngineMove(hash, mv),
+        )
+    }
 
+    // restarts the analysis whenever the shown position changes; it pauses while the computer thinks
+    fn analyse(&mut self) -> Command<Message> {
+        let wanted = (self.analysis && !self.thinking && self.pending_promotion.is_none()).then(|| self.position.hash());
+        if wanted == self.analysed {
+            return Command::none();
+        }
+        if wanted.is_some() {
+            if let Some(starting) = self.start_external() {
+                return starting;
             }
-            // attck
-            if w + 1 < CHESS_LEHGT {
-                if let Some(x) = board[h + 1][w + 1].piece_state {
-                    if x.owner != piece.owner {
-                        high_light.push(Point { h: h + 1, w : w + 1});
-                    }
-                }
-                if let Some(x) = en_passant {
-                    if (x.h == h + 1) && (x.w == w + 1) {
-                        high_light.push(Point { h: h + 1, w : w + 1});
-                    }
-                }
-            }
+        }
 
-            if w as i32 - 1 >= 0 {
-                if let Some(x) = board[h + 1][w - 1].piece_state {
-                    if x.owner != piece.owner {
-                        high_light.push(Point { h: h + 1, w : w - 1});
-                    }
-                }
-                if let Some(x) = en_passant {
-                    if (x.h == h + 1) && (x.w == w - 1) {
-                        high_light.push(Point { h: h + 1, w : w - 1});
-                    }
-                }
-            }
-        },
-        Player::White => {
-            if h == 6 {
-                let mut dh = 1;
-                while dh <= 2 {
-                    if board[h - dh][w].piece_state == None {
-                        high_light.push(Point { h: h - dh, w });
-                    } else {
-                        break;
-                    }
-                    dh += 1;
-                }
-            } else {
-                if board[h - 1][w].piece_state == None {
-                    high_light.push(Point { h: h - 1, w });
-                }
+        self.analysis_stop.store(true, Ordering::Relaxed);
+        self.analysis_lines = Vec::new();
+        self.preview = None;
+        self.analysed = wanted;
+        if wanted.is_none() {
+            return Command::none();
+        }
 
-            }
-            // attck
-            if w + 1 < CHESS_LEHGT {
-                if let Some(x) = board[h - 1][w + 1].piece_state {
-                    if x.owner != piece.owner {
-                        high_light.push(Point { h: h - 1, w : w + 1});
-                    }
-                }
-                if let Some(x) = en_passant {
-                    if (x.h == h - 1) && (x.w == w + 1) {
-                        high_light.push(Point { h: h - 1, w : w + 1});
+        self.analysis_session += 1;
+        self.analysis_stop = Arc::new(AtomicBool::new(false));
+
+        let session = self.analysis_session;
+        let stop = self.analysis_stop.clone();
+        let lines = self.analysis_multi_pv;
+        let start = self.start_position.clone();
+        let moves: Vec<Move> = self.moves[..self.ply].iter().map(|record| record.mv.clone()).collect();
+        let position = self.position.clone();
+        let history: Vec<u64> = std::iter::once(&self.start_position)
+            .chain(self.moves[..self.ply].iter().map(|record| &record.position))
+            .take(self.ply)
+            .map(|position| position.hash())
+            .collect();
+        let engine = self.engine.clone();
+        let external = self.external.clone();
+        let new_game = external.is_some() && std::mem::take(&mut self.external_new_game);
+
+        let (sender, receiver) = mpsc::unbounded();
+        std::thread::spawn(move || {
+            let send = |info: &SearchInfo| {
+                let _ = sender.unbounded_send(info.clone());
+            };
+            match external {
+                Some(external) => {
+                    let mut external = external.lock().unwrap();
+                    if new_game {
+                        let _ = external.new_game();
                     }
-                }
+                    let _ = external.set_option("MultiPV", &lines.to_string());
+                    let _ = external.go(&start, &moves, &Limits::default(), &stop, send);
+                },
+                None => {
+                    let mut engine = engine.lock().unwrap();
+                    engine.set_skill_level(MAX_SKILL_LEVEL);
+                    engine.set_multi_pv(lines);
+                    engine.search(&position, &history, &Limits::default(), &stop, send);
+                },
             }
+        });
 
-            if w as i32 - 1 >= 0 {
-                if let Some(x) = board[h - 1][w - 1].piece_state {
-                    if x.owner != piece.owner {
-                        high_light.push(Point { h: h - 1, w : w - 1});
-                    }
-                }
-                if let Some(x) = en_passant {
-                    if (x.h == h - 1) && (x.w == w - 1) {
-                        high_light.push(Point { h: h - 1, w : w - 1});
-                    }
-                }
-            }
-        },
+        Command::run(receiver, move |info| Message::AnalysisInfo(session, info))
     }
 
-    high_light
-}
-
-fn move_able_queen(board: &Board, p: &Point) -> Vec<Point> {
-    let mut high_light: Vec<Point> = Vec::new();
-
-    high_light.extend(move_able_bishop(&board, &p));
-    high_light.extend(move_able_rook(&board, &p));
+    // the line list under the board: "depth 12  +0.35" and the PV moves to click through
+    fn view_analysis(&self) -> Element<'_, Message> {
+        let lines_picker = row![
+            text("lines").size(15.0),
+            pick_list(ANALYSIS_LINES, Some(self.analysis_multi_pv), Message::AnalysisLinesSelected).text_size(15.0),
+            button("back to game").on_press_maybe(self.preview.is_some().then_some(Message::ClosePreview)).padding(5),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        if self.analysis_lines.is_empty() {
+            return column![lines_picker, text("analysing...").size(15.0)].spacing(5).into();
+        }
 
-    high_light
-}
+        let lines = self.analysis_lines.iter().enumerate().fold(Column::new().spacing(2), |c, (i, info)| {
+            let header = text(format!("depth {}  {}", info.depth, score_label(info.score, self.position.turn)))
+                .size(15.0)
+                .width(Length::Fixed(110.0));
+
+            let mut position = self.position.clone();
+            let mut line = Row::new().spacing(2).push(header);
+            for (j, mv) in info.pv.iter().take(ANALYSIS_PV_MOVES).enumerate() {
+                let san = pgn::san(&position, mv);
+                position.make_move(mv);
+
+                let shown = matches!(self.preview, Some((line, index, _)) if line == i && index == j);
+                line = line.push(
+                    button(text(san).size(14.0))
+                        .on_press(Message::Preview(i, j))
+                        .padding([2, 4])
+                        .style(if shown { button::primary } else { button::text })
+                );
+            }
+            c.push(line.align_y(Alignment::Center))
+        });
 
-fn update_high_light(board: &Board, light: Vec<Point>, value: bool) -> Board {
-    let mut cloned_board: Board = board.iter()
-        .map(|inner_vector| inner_vector.clone())
-        .collect();
+        column![lines_picker, lines].spacing(5).width(Length::Fixed(730.0)).into()
+    }
 
-    for p in light {
-        cloned_board[p.h][p.w].high_light = value;
+    fn title(&self) -> String {
+        String::from("chess - Iced")
     }
-    cloned_board
-}
 
+    fn subscription(&self) -> iced::Subscription<Message> {
+        let keys = keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+            keyboard::Key::Named(keyboard::key::Named::ArrowLeft) => Some(Message::Previous),
+            keyboard::Key::Named(keyboard::key::Named::ArrowRight) => Some(Message::Next),
+            keyboard::Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("z") => match modifiers.shift() {
+                true => Some(Message::Redo),
+                false => Some(Message::Undo),
+            },
+            keyboard::Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("y") => Some(Message::Redo),
+            keyboard::Key::Character(c) if !modifiers.command() && c.eq_ignore_ascii_case("f") => Some(Message::FlipBoard),
+            _ => None,
+        });
+
+        // ticks only while a clock runs, often enough for the tenths
+        let ticks = match self.clock.as_ref().and_then(Clock::running) {
+            Some(_) => iced::time::every(Duration::from_millis(100)).map(|_| Message::Tick),
+            None => iced::Subscription::none(),
+        };
 
+        iced::Subscription::batch([keys, ticks])
+    }
 
-impl Chess {
+    fn view_move_list(&self) -> Element<'_, Message> {
+        let mut list = Column::new().spacing(2);
+        let mut number = self.start_position.fullmove_number;
+        let mut turn = self.start_position.turn;
+        let mut line = Row::new().spacing(5);
+
+        for (i, record) in self.moves.iter().enumerate() {
+            if turn == Player::White || i == 0 {
+                let label = match turn {
+                    Player::White => format!("{}.", number),
+                    Player::Black => format!("{}...", number),
+                };
+                line = line.push(text(label).width(Length::Fixed(40.0)));
+            }
 
-    fn new() -> (Self, Command<Message>) {
-        let board = init_board();
+            let ply = i + 1;
+            let san = button(text(record.san.clone()))
+                .on_press(Message::GoTo(ply))
+                .width(Length::Fixed(70.0))
+                .style(move |theme, status| match ply == self.ply {
+                    true => button::primary(theme, status),
+                    false => button::text(theme, status),
+                });
+            line = line.push(san);
+
+            if turn == Player::Black {
+                list = list.push(line);
+                line = Row::new().spacing(5);
+                number += 1;
+            }
+            turn = turn.enemy();
+        }
+        if turn == Player::Black {
+            list = list.push(line);
+        }
 
-        (
-            Self {
-                board,
-                turn: Player::White,
-                choose: None,
-                
-                en_passant: None,
-                castling: Castling { w_r_rook:false, b_king: false,b_l_rook:false, b_r_rook:false,w_king:false,w_l_rook:false},
-                promotion_popup: false,
-                game_over: false,
-            },
-            Command::none()
-        )
+        let last = self.moves.len();
+        column![
+            scrollable(list).height(Length::Fill).width(Length::Fill),
+            row![
+                button("|<").on_press(Message::GoTo(0)),
+                button("<").on_press(Message::Previous),
+                button(">").on_press(Message::Next),
+                button(">|").on_press(Message::GoTo(last)),
+            ].spacing(5),
+        ]
+        .spacing(10)
+        .width(Length::Fixed(220.0))
+        .height(Length::Fixed(640.0))
+        .into()
     }
 
-    fn title(&self) -> String {
-        String::from("chess - Iced")
+    fn view_new_game<'a>(&self, draft: &'a Settings) -> Element<'a, Message> {
+        let computer = draft.opponent != Opponent::Human;
+        let clock_valid = draft.time_control.trim().is_empty() || draft.time_control().is_some();
+        let valid = (draft.clone().set_limit_value(&self.limit_input) || !computer) && clock_valid;
+
+        let mut dialog = column![
+            text("new game").size(20.0),
+            pick_list(Opponent::ALL, Some(draft.opponent), Message::OpponentSelected).padding(5),
+        ]
+        .spacing(10);
+
+        if computer {
+            dialog = dialog.push(row![
+                pick_list(LimitKind::ALL, Some(draft.limit), Message::LimitSelected).padding(5),
+                text_input("value", &self.limit_input)
+                    .on_input(Message::LimitInput)
+                    .on_submit(Message::StartGame)
+                    .padding(5),
+            ].spacing(10));
+            dialog = dialog.push(row![
+                text(format!("skill level {}", draft.skill_level)).width(Length::Fixed(120.0)),
+                slider(0..=MAX_SKILL_LEVEL, draft.skill_level, Message::SkillChanged),
+            ].spacing(10));
+        }
+
+        dialog = dialog.push(row![
+            text_input("White", &draft.names[Player::White as usize])
+                .on_input(|name| Message::NameInput(Player::White, name))
+                .padding(5),
+            text_input("Black", &draft.names[Player::Black as usize])
+                .on_input(|name| Message::NameInput(Player::Black, name))
+                .padding(5),
+        ].spacing(10));
+
+        let preset = TimeControl::PRESETS.iter().find(|preset| **preset == draft.time_control).copied();
+        dialog = dialog.push(row![
+            pick_list(TimeControl::PRESETS, preset, |preset| Message::TimeControlInput(preset.to_string())).padding(5),
+            text_input("clock, e.g. 5+3, 3+2d or 40/90+30; empty for none", &draft.time_control)
+                .on_input(Message::TimeControlInput)
+                .on_submit(Message::StartGame)
+                .padding(5),
+        ].spacing(10));
+
+        dialog = dialog.push(
+            text_input("external UCI engine, empty for the built-in one", &draft.engine_path)
+                .on_input(Message::EnginePathInput)
+                .padding(5)
+        );
+
+        dialog = dialog.push(
+            button("start").on_press_maybe(valid.then_some(Message::StartGame)).padding(5)
+        );
+
+        container(dialog)
+            .width(Length::Fixed(400.0))
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::MoveAble(p, piece_state) => {
                 
-                if let Some((point, piece_state)) = &self.choose {
-
-                    let light = match piece_state.piece {
-                        ChessPiece::Rook    => { move_able_rook(&self.board, &point) },
-                        ChessPiece::Bishop  => { move_able_bishop(&self.board, &point) },
-                        ChessPiece::King    => { move_able_king(&self.board, &point) },
-                        ChessPiece::Knight  => { move_able_knight(&self.board, &point) },
-                        ChessPiece::Queen   => { move_able_queen(&self.board, &point) },
+                self.high_light = Vec::new();
+                self.choose = None;
 
-                        ChessPiece::Pawn    => { move_able_pawn(&self.board, &point, &self.en_passant) },
-                    };
-                
-                    self.board = update_high_light(&self.board, light, false);
+                // the first click on a previewed board goes back to the game
+                if self.preview.take().is_some() {
+                    return Command::none();
                 }
 
                 if let Some(x) = piece_state {
-                    if x.owner == self.turn {
+                    if x.owner == self.position.turn && self.can_play() {
                         
-                        let light = match x.piece {
-                            ChessPiece::Rook    => { move_able_rook(&self.board, &p) },
-                            ChessPiece::Bishop  => { move_able_bishop(&self.board, &p) },
-                            ChessPiece::King    => { move_able_king(&self.board, &p) },
-                            ChessPiece::Knight  => { move_able_knight(&self.board, &p) },
-                            ChessPiece::Queen   => { move_able_queen(&self.board, &p) },
-    
-                            ChessPiece::Pawn    => { move_able_pawn(&self.board, &p, &self.en_passant) },
-                        };
-                        self.board = update_high_light(&self.board, light, true);
+                        self.high_light = legal_move_able(&self.position.board, &p, &self.position.en_passant, &self.position.castling);
                         self.choose = Some((p, x));
                     }
                 }
@@ -545,132 +899,481 @@ impl Chess {
             },
             Message::Move(p) => {
                 
-                if let Some((point, piece_state)) = self.choose.clone() {
-                    // en passant kill
-                    if piece_state.piece == ChessPiece::Pawn {
-                        if let Some(x) = &self.en_passant {
-                            if x.h == p.h && x.w == p.w {
-                                match piece_state.owner {
-                                    Player::White => { self.board[x.h + 1][x.w] = TileState {piece_state: None, high_light: false}; },
-                                    Player::Black => { self.board[x.h - 1][x.w] = TileState {piece_state: None, high_light: false}; },
-                                }   
-                            }
-                        }
-                    }
-                    self.en_passant = None;
-                    
-                    // en passant able
-                    if piece_state.piece == ChessPiece::Pawn {
-                        if ((p.h as i32 - point.h as i32).abs() == 2) && (p.w - point.w == 0) {
-                            if piece_state.owner == Player::White && p.h == 4 {
-                                self.en_passant = Some(Point {h: p.h+1, w: p.w});
-                            } else if piece_state.owner == Player::Black && p.h == 3 {
-                                self.en_passant = Some(Point {h: p.h-1, w: p.w});
-                            }
-                        }                    
-                    }
-
-                    if let Some(x) = self.board[p.h][p.w].piece_state {
-                        if x.piece == ChessPiece::King {
-                            self.game_over = true;
-                        }
-                    }
-
-                    // move
-                    let light = match piece_state.piece {
-                        ChessPiece::Rook    => { move_able_rook(&self.board, &point) },
-                        ChessPiece::Bishop  => { move_able_bishop(&self.board, &point) },
-                        ChessPiece::King    => { move_able_king(&self.board, &point) },
-                        ChessPiece::Knight  => { move_able_knight(&self.board, &point) },
-                        ChessPiece::Queen   => { move_able_queen(&self.board, &point) },
+                self.high_light = Vec::new();
+                if let Some((point, piece_state)) = self.choose.take().filter(|_| self.can_play()) {
 
-                        ChessPiece::Pawn    => { move_able_pawn(&self.board, &point, &self.en_passant) },
+                    let mv = Move { from: point, to: p, promotion: None };
+                    let last_rank = match piece_state.owner {
+                        Player::White => 0,
+                        Player::Black => CHESS_LEHGT - 1,
                     };
-                    self.board = update_high_light(&self.board, light, false);
 
-                    self.board[p.h][p.w] = self.board[point.h][point.w];
-                    self.board[point.h][point.w] = TileState {piece_state: None, high_light: false};
-                    self.choose = None;
-                    
-
-                    // Promotion
-                    if (piece_state.piece == ChessPiece::Pawn) && (((piece_state.owner == Player::White) && (p.h == 0)) || ((piece_state.owner == Player::Black) && (p.h == 7))) {
-                        self.promotion_popup = true;
-                    }
-                    
-                    
-                    self.turn = match self.turn {
-                        Player::White => { Player::Black },
-                        Player::Black => { Player::White }
+                    // the move waits for the piece to promote to
+                    if piece_state.piece == ChessPiece::Pawn && mv.to.h == last_rank {
+                        self.pending_promotion = Some(mv);
+                    } else {
+                        self.play_move(mv);
                     }
                 }
-                
 
+                self.refresh()
+            },
+            Message::Promotion(piece) => {
+                if let Some(mv) = self.pending_promotion.take().filter(|_| self.can_play()) {
+                    self.play_move(Move { promotion: Some(piece), ..mv });
+                }
+                self.refresh()
+            },
+            Message::CancelPromotion => {
+                // the pawn never left its square
+                self.pending_promotion = None;
+                self.refresh()
+            },
+            Message::ClaimDraw => {
+                if let Some(reason) = self.claimable_draw() {
+                    self.game_result = Some(GameResult::Draw(reason));
+                    self.final_result = self.game_result;
+                    self.result_dismissed = false;
+                }
 
+                self.refresh()
+            },
+            Message::CopyFen => {
+                iced::clipboard::write(self.position.to_fen())
+            },
+            Message::FenInput(fen) => {
+                self.fen_input = fen;
+                self.fen_error = None;
                 Command::none()
             },
-            Message::Promotion(piece_state) => {
-                match piece_state.owner {
-                    Player::White => {
-                        for i in 0..CHESS_LEHGT {
-                            if let Some(x) = self.board[0][i].piece_state {
-                                if x.piece == ChessPiece::Pawn {
-                                    self.board[0][i].piece_state = Some(piece_state);
-                                    break;
-                                }
-                            }
-                        }
+            Message::LoadFen => {
+                match Position::from_fen(&self.fen_input) {
+                    Ok(position) => {
+                        // both sides are moved by hand until the next new game
+                        self.opponent = Opponent::Human;
+                        self.set_position(position);
+                        self.fen_error = None;
                     },
-                    Player::Black => {
-                        for i in 0..CHESS_LEHGT {
-                            if let Some(x) = self.board[7][i].piece_state {
-                                if x.piece == ChessPiece::Pawn {
-                                    self.board[7][i].piece_state = Some(piece_state);
-                                    break;
-                                }
-                            }
+                    Err(e) => {
+                        self.fen_error = Some(e.to_string());
+                    },
+                }
+                self.refresh()
+            },
+            Message::PgnPathInput(path) => {
+                self.pgn_path = path;
+                self.pgn_message = None;
+                Command::none()
+            },
+            Message::ExportPgn => {
+                let sans: Vec<String> = self.moves.iter().map(|record| record.san.clone()).collect();
+                let names = [self.names[0].as_str(), self.names[1].as_str()];
+                let pgn = pgn::export(&self.start_position, &sans, &self.final_result, names);
+                self.pgn_message = match std::fs::write(&self.pgn_path, pgn) {
+                    Ok(_) => Some(format!("saved {}", self.pgn_path)),
+                    Err(e) => Some(format!("could not write {}: {}", self.pgn_path, e)),
+                };
+                Command::none()
+            },
+            Message::ImportPgn => {
+                let games = std::fs::read_to_string(&self.pgn_path)
+                    .map_err(|e| format!("could not read {}: {}", self.pgn_path, e))
+                    .and_then(|text| pgn::parse_games(&text).map_err(|e| e.to_string()));
+
+                match games {
+                    Ok(games) if games.is_empty() => {
+                        self.pgn_message = Some(format!("no games in {}", self.pgn_path));
+                    },
+                    Ok(games) => {
+                        self.pgn_games = games;
+                        if self.pgn_games.len() == 1 {
+                            self.load_game(0);
                         }
                     },
+                    Err(e) => {
+                        self.pgn_message = Some(e);
+                    },
                 }
-
-
-                self.promotion_popup = false;
+                self.refresh()
+            },
+            Message::LoadGame(index) => {
+                self.load_game(index);
+                self.refresh()
+            },
+            Message::CloseGamePicker => {
+                self.pgn_games = Vec::new();
+                Command::none()
+            },
+            Message::GoTo(ply) => {
+                self.go_to(ply);
+                self.refresh()
+            },
+            Message::Previous => {
+                if self.ply > 0 {
+                    self.go_to(self.ply - 1);
+                }
+                self.refresh()
+            },
+            Message::Next => {
+                self.go_to(self.ply + 1);
+                self.refresh()
+            },
+            Message::Undo => {
+                // against the computer take back its reply as well
+                while let Some(snapshot) = self.undo_stack.pop() {
+                    self.redo_stack.push(self.snapshot());
+                    self.restore(snapshot);
+                    if !self.computer_to_move() {
+                        break;
+                    }
+                }
+                self.refresh()
+            },
+            Message::Redo => {
+                while let Some(snapshot) = self.redo_stack.pop() {
+                    self.undo_stack.push(self.snapshot());
+                    self.restore(snapshot);
+                    if !self.computer_to_move() {
+                        break;
+                    }
+                }
+                self.refresh()
+            },
+            Message::NameInput(player, name) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.names[player as usize] = name;
+                }
+                Command::none()
+            },
+            Message::ResumeGame => {
+                let Some(game) = self.resume_offer.take() else {
+                    return Command::none();
+                };
+
+                // the saved game's mode holds for this game, the remembered settings stay as they are
+                self.opponent = game.opponent;
+                self.names = game.names;
+                self.time_control = game.time_control;
+                self.set_position(game.start);
+                for mv in game.moves {
+                    if self.game_result.is_some() {
+                        break;
+                    }
+                    self.apply_move(mv);
+                }
+                self.clock = TimeControl::parse(&self.time_control).map(|control| match game.clocks {
+                    Some((remaining, moves)) => Clock::resume(control, remaining, moves),
+                    None => Clock::new(control),
+                });
+                self.refresh()
+            },
+            Message::DiscardSavedGame => {
+                self.resume_offer = None;
+                self.refresh()
+            },
+            Message::FlipBoard => {
+                self.flipped = !self.flipped;
+                Command::none()
+            },
+            Message::AutoOrientation(auto) => {
+                self.settings.auto_orientation = auto;
+                self.flipped = false;
+                if let Err(e) = self.settings.save() {
+                    eprintln!("could not save settings: {}", e);
+                }
+                Command::none()
+            },
+            Message::DismissResult => {
+                self.result_dismissed = true;
                 Command::none()
             },
             Message::Reset => {
-                let board = init_board();
+                self.set_position(Position::new());
+                self.refresh()
+            },
+            Message::ToggleAnalysis => {
+                self.analysis = !self.analysis;
+                self.refresh()
+            },
+            Message::AnalysisInfo(session, info) => {
+                // nothing from an analysis that was already replaced
+                if session == self.analysis_session && self.analysed.is_some() {
+                    match self.analysis_lines.iter_mut().find(|line| line.multipv == info.multipv) {
+                        Some(line) => { *line = info; },
+                        None => {
+                            self.analysis_lines.push(info);
+                            self.analysis_lines.sort_by_key(|line| line.multipv);
+                        },
+                    }
+                }
+                Command::none()
+            },
+            Message::AnalysisLinesSelected(lines) => {
+                self.analysis_multi_pv = lines;
+                // starts over with the new number of lines
+                self.analysis_stop.store(true, Ordering::Relaxed);
+                self.analysed = None;
+                self.refresh()
+            },
+            Message::Preview(line, index) => {
+                if let Some(info) = self.analysis_lines.get(line) {
+                    let mut position = self.position.clone();
+                    for mv in info.pv.iter().take(index + 1) {
+                        position.make_move(mv);
+                    }
+                    self.preview = Some((line, index, position));
+                    self.choose = None;
+                    self.high_light = Vec::new();
+                }
+                Command::none()
+            },
+            Message::ClosePreview => {
+                self.preview = None;
+                Command::none()
+            },
+            Message::OpenNewGame => {
+                self.limit_input = self.settings.limit_value();
+                self.new_game = Some(self.settings.clone());
+                Command::none()
+            },
+            Message::CloseNewGame => {
+                self.new_game = None;
+                Command::none()
+            },
+            Message::OpponentSelected(opponent) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.opponent = opponent;
+                }
+                Command::none()
+            },
+            Message::LimitSelected(limit) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.limit = limit;
+                    self.limit_input = draft.limit_value();
+                }
+                Command::none()
+            },
+            Message::LimitInput(value) => {
+                self.limit_input = value;
+                Command::none()
+            },
+            Message::SkillChanged(level) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.skill_level = level;
+                }
+                Command::none()
+            },
+            Message::StartGame => {
+                if let Some(mut draft) = self.new_game.take() {
+                    // the limit only matters against the computer
+                    if (!draft.set_limit_value(&self.limit_input) && draft.opponent != Opponent::Human)
+                        || (!draft.time_control.trim().is_empty() && draft.time_control().is_none()) {
+                        self.new_game = Some(draft);
+                        return Command::none();
+                    }
 
-                self.board = board;
-                self.turn = Player::White;
-                self.choose = None;
+                    // the game still starts, only the remembered choice is lost
+                    if let Err(e) = draft.save() {
+                        eprintln!("could not save settings: {}", e);
+                    }
+                    if draft.engine_path != self.settings.engine_path {
+                        self.external = None;
+                        self.engine_message = None;
+                    }
+                    self.external_failed = false;
+                    self.settings = draft;
+                    self.use_settings();
+                    self.set_position(Position::new());
+                }
+                self.refresh()
+            },
+            Message::TimeControlInput(control) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.time_control = control;
+                }
+                Command::none()
+            },
+            Message::Tick => {
+                let now = Instant::now();
+                let Some(player) = self.clock.as_ref().and_then(|clock| clock.flagged(now)) else {
+                    // the time spent on the move so far is not won back by closing the window
+                    if now - self.saved_at >= AUTOSAVE_INTERVAL {
+                        self.autosave();
+                    }
+                    return Command::none();
+                };
+
+                // a flag only loses when the opponent could still mate
+                self.final_result = Some(match has_mating_material(&self.game_position().board, player.enemy()) {
+                    true => GameResult::Win(player.enemy(), WinReason::Timeout),
+                    false => GameResult::Draw(DrawReason::TimeoutVsInsufficientMaterial),
+                });
+                self.result_dismissed = false;
+                // back to the end of the game, where the result shows
+                self.go_to(self.moves.len());
+                self.refresh()
+            },
+            Message::EnginePathInput(path) => {
+                if let Some(draft) = &mut self.new_game {
+                    draft.engine_path = path;
+                }
+                Command::none()
+            },
+            Message::ExternalEngineStarted(path, engine) => {
+                self.external_starting = false;
+
+                if path == self.settings.engine_path {
+                    match engine {
+                        Ok(engine) => {
+                            self.engine_message = Some(format!("using {}", engine.lock().unwrap().name));
+                            self.external = Some(engine);
+                            self.external_new_game = true;
+                        },
+                        Err(e) => {
+                            self.engine_message = Some(format!("{}, using the built-in engine", e));
+                            self.external_failed = true;
+                        },
+                    }
+                }
+                self.refresh()
+            },
+            Message::EngineMove(hash, mv) => {
+                self.thinking = false;
+
+                let mv = match mv {
+                    Ok(mv) => mv,
+                    Err(e) => {
+                        self.engine_message = Some(format!("{}, using the built-in engine", e));
+                        self.external = None;
+                        self.external_failed = true;
+                        return self.refresh();
+                    },
+                };
+
+                // the game may have moved on while the engine was thinking
+                let game = self.game_position();
+                if let Some(mv) = mv {
+                    if game.hash() == hash && self.opponent == Opponent::Computer(game.turn)
+                        && self.final_result.is_none() && self.pending_promotion.is_none() {
+                        // the reply goes on the end of the game, the shown ply stays unless the game is over
+                        let shown = (self.ply != self.moves.len()).then_some(self.ply);
+                        self.go_to(self.moves.len());
+                        self.play_move(mv);
+                        if let Some(ply) = shown.filter(|_| self.final_result.is_none()) {
+                            self.go_to(ply);
+                        }
+                    }
+                }
 
-                self.promotion_popup = false;                
-                self.en_passant = None;
-                self.castling = Castling { w_r_rook:false, b_king: false,b_l_rook:false, b_r_rook:false,w_king:false,w_l_rook:false};
-                self.game_over = false;
-                Command::none()     
+                self.refresh()
             },
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        
+    // "Black  4:05", lit while it runs
+    fn view_clock(&self, player: Player) -> Option<Element<'_, Message>> {
+        let clock = self.clock.as_ref()?;
+        let running = clock.running() == Some(player);
+
+        let face = container(
+            row![
+                text(&self.names[player as usize]).size(20.0).width(Length::Fill),
+                text(clock::format(clock.remaining(player, Instant::now()))).size(24.0),
+            ]
+            .align_y(Alignment::Center)
+        )
+        .width(Length::Fixed(640.0))
+        .padding([4, 10]);
 
-        let board = container((0..CHESS_LEHGT).into_iter().fold(Column::new() ,|c, i|
-                c.push(Element::from(
-                    (0..CHESS_LEHGT).into_iter().fold(Row::new(),|c, j|
-                        c.push(
-                            view_tile(&self.board[i][j], i, j)
-                        )
-                    )
-                ))
-            ));
+        Some(match running {
+            true => face.style(container::rounded_box).into(),
+            false => face.into(),
+        })
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        // a previewed analysis line takes the place of the game on the board
+        let shown = self.preview.as_ref().map_or(&self.position, |(_, _, position)| position);
+        let checked_king = find_king(&shown.board, shown.turn)
+            .filter(|king| attack_map(&shown.board, shown.turn.enemy())[king.h][king.w]);
+
+        let mut status = match checked_king {
+            Some(_) => format!("{} is in check", shown.turn.name()),
+            None => format!("{} to move", shown.turn.name()),
+        };
+        if self.preview.is_some() {
+            status.push_str(" (analysis preview)");
+        }
+        if self.thinking {
+            status.push_str(", computer is thinking");
+        }
+        let analysis_label = if self.analysis { "stop analysis" } else { "analyse" };
+
+        let movable = (self.preview.is_none() && self.can_play())
+            .then_some(shown.turn);
+        let last_move = match &self.preview {
+            Some((line, index, _)) => self.analysis_lines.get(*line).and_then(|info| info.pv.get(*index)),
+            None => self.ply.checked_sub(1).map(|ply| &self.moves[ply].mv),
+        };
+        let bottom = self.bottom_player();
+        let board = BoardView::new(shown.board, Message::MoveAble, Message::Move)
+            .targets(&self.high_light)
+            .checked(checked_king)
+            .last_move(last_move.map(|mv| (mv.from.clone(), mv.to.clone())))
+            .selected(self.choose.as_ref().map(|(p, _)| p.clone()))
+            .movable(movable)
+            .promotion(self.pending_promotion.as_ref().map(|mv| mv.to.clone()), Message::Promotion, Message::CancelPromotion)
+            .flipped(bottom == Player::Black);
         
         let content = container(
                 column!(
-                    button("reset").on_press(Message::Reset).padding(5),
-                    board,
-                ).align_x(Alignment::Center)
+                    row![
+                        button("reset").on_press(Message::Reset).padding(5),
+                        button("undo").on_press_maybe((!self.undo_stack.is_empty()).then_some(Message::Undo)).padding(5),
+                        button("redo").on_press_maybe((!self.redo_stack.is_empty()).then_some(Message::Redo)).padding(5),
+                        button("claim draw").on_press_maybe(self.claimable_draw().map(|_| Message::ClaimDraw)).padding(5),
+                        button("new game").on_press(Message::OpenNewGame).padding(5),
+                        button(analysis_label).on_press(Message::ToggleAnalysis).padding(5),
+                        button("flip").on_press(Message::FlipBoard).padding(5),
+                        checkbox("auto-rotate", self.settings.auto_orientation).on_toggle(Message::AutoOrientation),
+                    ].spacing(10).align_y(Alignment::Center),
+                    text(status).size(20.0),
+                    text(self.engine_message.clone().unwrap_or_default()).size(15.0),
+                    Row::new()
+                        .push_maybe(self.analysis.then(|| {
+                            view_eval_bar(self.analysis_lines.first().map(|line| line.score), self.position.turn, bottom)
+                        }))
+                        .push(
+                            Column::new()
+                                .push_maybe(self.view_clock(bottom.enemy()))
+                                .push(board)
+                                .push_maybe(self.view_clock(bottom))
+                                .spacing(5)
+                        )
+                        .push(self.view_move_list())
+                        .spacing(10),
+                )
+                .push_maybe(self.analysis.then(|| self.view_analysis()))
+                .push(column!(
+                    row![
+                        text_input("FEN", &self.fen_input)
+                            .on_input(Message::FenInput)
+                            .on_submit(Message::LoadFen)
+                            .padding(5),
+                        button("load").on_press(Message::LoadFen).padding(5),
+                        button("copy FEN").on_press(Message::CopyFen).padding(5),
+                    ].spacing(10).width(Length::Fixed(640.0)),
+                    text(self.fen_error.clone().unwrap_or_default()).size(15.0).color(color!(0xe0, 0x4b, 0x4b)),
+                    row![
+                        text_input("PGN file", &self.pgn_path)
+                            .on_input(Message::PgnPathInput)
+                            .padding(5),
+                        button("import PGN").on_press(Message::ImportPgn).padding(5),
+                        button("export PGN").on_press(Message::ExportPgn).padding(5),
+                    ].spacing(10).width(Length::Fixed(640.0)),
+                    text(self.pgn_message.clone().unwrap_or_default()).size(15.0),
+                ).align_x(Alignment::Center))
+                .align_x(Alignment::Center)
             )
             .width(Length::Fill)
             .height(Length::Fill)
@@ -678,46 +1381,70 @@ impl Chess {
             
 
 
-        if self.promotion_popup {
-            
-            let (text_color, owner) = match self.turn {
-                Player::White => (color!(0x000000), Player::Black),
-                Player::Black => (color!(0xffffff), Player::White),
-            };
-
-            let promotion_modal = container(
+        if let Some(game) = &self.resume_offer {
+            let resume_modal = container(
                 column![
-                    text("promotion").size(20.0),
+                    text("resume your last game?").size(20.0),
+                    text(format!("{} vs {}, {} moves played", game.names[0], game.names[1], game.moves.len())).size(15.0),
                     row![
-                        button(text("♛").size(50.0).color(text_color).shaping(text::Shaping::Advanced)).on_press(Message::Promotion(PieceState {owner, piece: ChessPiece::Queen}))
-                            .style( |_,state| { custom_theme::ChessStyle::bright_button_wrapper(state) } ),
-                        
-                        button(text("♝").size(50.0).color(text_color).shaping(text::Shaping::Advanced)).on_press(Message::Promotion(PieceState {owner, piece: ChessPiece::Bishop}))
-                            .style( |_,state| { custom_theme::ChessStyle::dark_button_wrapper(state) } ),
-
-                        button(text("♞").size(50.0).color(text_color).shaping(text::Shaping::Advanced)).on_press(Message::Promotion(PieceState {owner, piece: ChessPiece::Knight}))
-                            .style( |_,state| { custom_theme::ChessStyle::bright_button_wrapper(state) } ),
-                        
-                        button(text("♜").size(50.0).color(text_color).shaping(text::Shaping::Advanced)).on_press(Message::Promotion(PieceState {owner, piece: ChessPiece::Rook}))
-                            .style( |_,state| { custom_theme::ChessStyle::dark_button_wrapper(state) } ),
-                    ]
+                        button("resume").on_press(Message::ResumeGame).padding(5),
+                        button("new game").on_press(Message::DiscardSavedGame).padding(5),
+                    ].spacing(10),
                 ]
+                .spacing(10)
                 .align_x(Alignment::Center)
+            )
+            .padding(10)
+            .style(container::rounded_box);
+
+            // a stray click must not throw the saved game away
+            modal::modal(content, resume_modal, None)
+
+        } else if let Some(draft) = &self.new_game {
+            modal::modal(content, self.view_new_game(draft), Some(Message::CloseNewGame))
+
+        } else if !self.pgn_games.is_empty() {
+            let games = self.pgn_games.iter().enumerate().fold(Column::new().spacing(5), |c, (i, game)|
+                c.push(
+                    button(text(format!("{}  {}  {}", game.title(i), game.tag("Date").unwrap_or("?"), game.result)))
+                        .on_press(Message::LoadGame(i))
+                        .width(Length::Fill)
+                )
             );
 
-            modal::modal_no_skip(content, promotion_modal).into()
+            let game_picker_modal = container(
+                column![
+                    text("choose a game").size(20.0),
+                    scrollable(games).height(Length::Fixed(400.0)),
+                ]
+                .spacing(10)
+            )
+            .width(Length::Fixed(500.0))
+            .padding(10)
+            .style(container::rounded_box);
 
-        } else if self.game_over {
-            let winner = match self.turn {
-                Player::Black => { String::from("White") },
-                Player::White => { String::from("Black") },
-            };
+            modal::modal(content, game_picker_modal, Some(Message::CloseGamePicker))
 
+        } else if let Some(result) = self.game_result.as_ref().filter(|_| !self.result_dismissed) {
             let game_over_modal = container(
-                text(format!("Win {}", winner)).size(50.0)
-            ).style(container::rounded_box);
+                column![
+                    text(result.title()).size(50.0),
+                    text(result.reason()).size(20.0),
+                    row![
+                        button("export PGN").on_press(Message::ExportPgn).padding(5),
+                        button("new game").on_press(Message::Reset).padding(5),
+                        button("close").on_press(Message::DismissResult).padding(5),
+                    ].spacing(10),
+                    text(self.pgn_message.clone().unwrap_or_default()).size(15.0),
+                ]
+                .spacing(10)
+                .align_x(Alignment::Center)
+            )
+            .padding(10)
+            .style(container::rounded_box);
 
-            modal::modal(content, game_over_modal, Message::Reset).into()
+            // closing it keeps the game to look through and export
+            modal::modal(content, game_over_modal, Some(Message::DismissResult))
 
         } else {
             content.into()
@@ -1,58 +1,34 @@
 use iced::{ Element, Color };
 use iced::widget::{ container, stack, opaque, mouse_area, center };
 
+// `on_blur` is sent for a click outside the content; None leaves such clicks without effect
 pub fn modal<'a, Message>(
     base: impl Into<Element<'a, Message>>,
     content: impl Into<Element<'a, Message>>,
-    on_blur: Message,
+    on_blur: Option<Message>,
 ) -> Element<'a, Message>
 where
     Message: Clone + 'a,
 {
-    stack![
-        base.into(),
-        opaque(
-            mouse_area(center(opaque(content)).style(|_theme| {
-                container::Style {
-                    background: Some(
-                        Color {
-                            a: 0.8,
-                            ..Color::BLACK
-                        }
-                        .into(),
-                    ),
-                    ..container::Style::default()
+    let mut backdrop = mouse_area(center(opaque(content)).style(|_theme| {
+        container::Style {
+            background: Some(
+                Color {
+                    a: 0.8,
+                    ..Color::BLACK
                 }
-            }))
-            .on_press(on_blur)
-        )
-    ]
-    .into()
-}
+                .into(),
+            ),
+            ..container::Style::default()
+        }
+    }));
+    if let Some(on_blur) = on_blur {
+        backdrop = backdrop.on_press(on_blur);
+    }
 
-pub fn modal_no_skip<'a, Message>(
-    base: impl Into<Element<'a, Message>>,
-    content: impl Into<Element<'a, Message>>,
-) -> Element<'a, Message>
-where
-    Message: Clone + 'a,
-{
     stack![
         base.into(),
-        opaque(
-            mouse_area(center(opaque(content)).style(|_theme| {
-                container::Style {
-                    background: Some(
-                        Color {
-                            a: 0.8,
-                            ..Color::BLACK
-                        }
-                        .into(),
-                    ),
-                    ..container::Style::default()
-                }
-            }))
-        )
+        opaque(backdrop)
     ]
     .into()
-}
\ No newline at end of file
+}
@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chess_core::{Move, Player, Position};
+
+use crate::settings::{data_dir, Opponent};
+
+// the game in progress, written after every move so it survives closing the window
+#[derive(Debug, Clone)]
+pub struct SavedGame {
+    pub start: Position,
+    pub moves: Vec<Move>,
+    pub opponent: Opponent,
+    pub names: [String; 2],
+    // empty for a game without clocks
+    pub time_control: String,
+    // time left and moves made, White's first
+    pub clocks: Option<([Duration; 2], [u32; 2])>,
+}
+
+fn saved_game_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("game.txt"))
+}
+
+impl SavedGame {
+    // key=value lines like the settings file; None when there is no game or it does not replay
+    pub fn load() -> Option<SavedGame> {
+        let text = std::fs::read_to_string(saved_game_path()?).ok()?;
+
+        let mut start = None;
+        let mut moves = "";
+        let mut opponent = Opponent::Human;
+        let mut names = [String::from("White"), String::from("Black")];
+        let mut time_control = String::new();
+        let mut remaining = [None, None];
+        let mut moves_made = [0, 0];
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match (key.trim(), value.trim()) {
+                ("fen", fen) => { start = Position::from_fen(fen).ok(); },
+                ("moves", list) => { moves = list; },
+                ("opponent", key) => { opponent = Opponent::from_key(key)?; },
+                ("white_name", name) => { names[Player::White as usize] = name.to_string(); },
+                ("black_name", name) => { names[Player::Black as usize] = name.to_string(); },
+                ("time_control", control) => { time_control = control.to_string(); },
+                ("white_clock_ms", ms) => { remaining[Player::White as usize] = ms.parse().ok().map(Duration::from_millis); },
+                ("black_clock_ms", ms) => { remaining[Player::Black as usize] = ms.parse().ok().map(Duration::from_millis); },
+                ("white_clock_moves", n) => { moves_made[Player::White as usize] = n.parse().unwrap_or(0); },
+                ("black_clock_moves", n) => { moves_made[Player::Black as usize] = n.parse().unwrap_or(0); },
+                _ => {},
+            }
+        }
+
+        let start = start?;
+        let mut position = start.clone();
+        let mut list = Vec::new();
+        for uci in moves.split_whitespace() {
+            let mv = position.uci_move(uci)?;
+            position.make_move(&mv);
+            list.push(mv);
+        }
+
+        let clocks = match remaining {
+            [Some(white), Some(black)] => Some(([white, black], moves_made)),
+            _ => None,
+        };
+
+        Some(SavedGame { start, moves: list, opponent, names, time_control, clocks })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = saved_game_path().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no user data directory"))?;
+
+        let moves: Vec<String> = self.moves.iter().map(|mv| mv.uci()).collect();
+        let mut text = format!(
+            "fen={}\nmoves={}\nopponent={}\nwhite_name={}\nblack_name={}\ntime_control={}\n",
+            self.start.to_fen(), moves.join(" "), self.opponent.key(), self.names[0], self.names[1], self.time_control,
+        );
+        if let Some((remaining, moves_made)) = self.clocks {
+            text.push_str(&format!(
+                "white_clock_ms={}\nblack_clock_ms={}\nwhite_clock_moves={}\nblack_clock_moves={}\n",
+                remaining[0].as_millis(), remaining[1].as_millis(), moves_made[0], moves_made[1],
+            ));
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+
+    // a finished game is not offered again
+    pub fn remove() {
+        if let Some(path) = saved_game_path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chess_core::{Limits, Player, MAX_SKILL_LEVEL};
+
+use crate::clock::TimeControl;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opponent {
+    Human,
+    // the colour the computer plays
+    Computer(Player),
+}
+
+impl Opponent {
+    pub const ALL: [Opponent; 3] = [Opponent::Human, Opponent::Computer(Player::Black), Opponent::Computer(Player::White)];
+
+    // the name in the settings and saved game files
+    pub fn key(self) -> &'static str {
+        match self {
+            Opponent::Human => "human",
+            Opponent::Computer(Player::White) => "computer-white",
+            Opponent::Computer(Player::Black) => "computer-black",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Opponent> {
+        Opponent::ALL.into_iter().find(|opponent| opponent.key() == key)
+    }
+}
+
+impl fmt::Display for Opponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opponent::Human => write!(f, "two players"),
+            Opponent::Computer(Player::Black) => write!(f, "play White vs computer"),
+            Opponent::Computer(Player::White) => write!(f, "play Black vs computer"),
+        }
+    }
+}
+
+// what stops the computer's search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    Depth,
+    Nodes,
+    Time,
+}
+
+impl LimitKind {
+    pub const ALL: [LimitKind; 3] = [LimitKind::Depth, LimitKind::Nodes, LimitKind::Time];
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitKind::Depth => write!(f, "fixed depth"),
+            LimitKind::Nodes => write!(f, "node limit"),
+            LimitKind::Time => write!(f, "time per move (ms)"),
+        }
+    }
+}
+
+// the new-game choices, remembered between runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub opponent: Opponent,
+    pub limit: LimitKind,
+    pub depth: u32,
+    pub nodes: u64,
+    pub movetime_ms: u64,
+    pub skill_level: u32,
+    // a UCI executable to play and analyse with, empty for the built-in engine
+    pub engine_path: String,
+    // time control notation like "5+3", empty for a game without clocks
+    pub time_control: String,
+    // keeps the side to move, or the human against the computer, at the bottom
+    pub auto_orientation: bool,
+    // White's and Black's, for the clocks and PGN files
+    pub names: [String; 2],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            opponent: Opponent::Human,
+            limit: LimitKind::Time,
+            depth: 6,
+            nodes: 200_000,
+            movetime_ms: 1000,
+            skill_level: MAX_SKILL_LEVEL,
+            engine_path: String::new(),
+            time_control: String::new(),
+            auto_orientation: false,
+            names: [String::from("White"), String::from("Black")],
+        }
+    }
+}
+
+impl Settings {
+    pub fn limits(&self) -> Limits {
+        match self.limit {
+            LimitKind::Depth => Limits { depth: Some(self.depth), ..Limits::default() },
+            LimitKind::Nodes => Limits { nodes: Some(self.nodes), ..Limits::default() },
+            LimitKind::Time => Limits { movetime: Some(Duration::from_millis(self.movetime_ms)), ..Limits::default() },
+        }
+    }
+
+    // None for a game without clocks or notation that does not parse
+    pub fn time_control(&self) -> Option<TimeControl> {
+        TimeControl::parse(&self.time_control)
+    }
+
+    // the number behind the chosen limit, as the dialog edits it
+    pub fn limit_value(&self) -> String {
+        match self.limit {
+            LimitKind::Depth => self.depth.to_string(),
+            LimitKind::Nodes => self.nodes.to_string(),
+            LimitKind::Time => self.movetime_ms.to_string(),
+        }
+    }
+
+    // false for anything but a positive number
+    pub fn set_limit_value(&mut self, value: &str) -> bool {
+        let Some(n) = value.trim().parse::<u64>().ok().filter(|n| *n > 0) else {
+            return false;
+        };
+
+        match self.limit {
+            LimitKind::Depth => { self.depth = n.min(64) as u32; },
+            LimitKind::Nodes => { self.nodes = n; },
+            LimitKind::Time => { self.movetime_ms = n; },
+        }
+        true
+    }
+
+    // key=value lines; unknown or broken lines keep their defaults
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let Some(text) = settings_path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+            return settings;
+        };
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match (key.trim(), value.trim()) {
+                ("opponent", key) => { settings.opponent = Opponent::from_key(key).unwrap_or(settings.opponent); },
+                ("limit", "depth") => { settings.limit = LimitKind::Depth; },
+                ("limit", "nodes") => { settings.limit = LimitKind::Nodes; },
+                ("limit", "time") => { settings.limit = LimitKind::Time; },
+                ("depth", n) => { settings.depth = n.parse().unwrap_or(settings.depth); },
+                ("nodes", n) => { settings.nodes = n.parse().unwrap_or(settings.nodes); },
+                ("movetime_ms", n) => { settings.movetime_ms = n.parse().unwrap_or(settings.movetime_ms); },
+                ("skill_level", n) => { settings.skill_level = n.parse::<u32>().unwrap_or(settings.skill_level).min(MAX_SKILL_LEVEL); },
+                ("engine_path", path) => { settings.engine_path = path.to_string(); },
+                ("time_control", control) => { settings.time_control = control.to_string(); },
+                ("auto_orientation", flag) => { settings.auto_orientation = flag == "true"; },
+                ("white_name", name) => { settings.names[Player::White as usize] = name.to_string(); },
+                ("black_name", name) => { settings.names[Player::Black as usize] = name.to_string(); },
+                _ => {},
+            }
+        }
+
+        settings
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = settings_path().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no user data directory"))?;
+
+        let opponent = self.opponent.key();
+        let limit = match self.limit {
+            LimitKind::Depth => "depth",
+            LimitKind::Nodes => "nodes",
+            LimitKind::Time => "time",
+        };
+        let text = format!(
+            "opponent={}\nlimit={}\ndepth={}\nnodes={}\nmovetime_ms={}\nskill_level={}\nengine_path={}\ntime_control={}\nauto_orientation={}\nwhite_name={}\nblack_name={}\n",
+            opponent, limit, self.depth, self.nodes, self.movetime_ms, self.skill_level, self.engine_path, self.time_control,
+            self.auto_orientation, self.names[0], self.names[1],
+        );
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+}
+
+// the platform's per-user data directory with a "chess" folder in it
+pub fn data_dir() -> Option<PathBuf> {
+    let env = |key: &str| std::env::var_os(key).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    let base = if cfg!(target_os = "windows") {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|dir| dir.join("chess"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("settings.txt"))
+}